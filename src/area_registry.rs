@@ -0,0 +1,328 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::{PolyMesh, INVALID_AREA_ID, WALKABLE_AREA_ID};
+
+// The meaning of an area ID. See `AreaRegistry`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AreaInfo {
+  // A unique name for the area, e.g. "water".
+  pub name: String,
+  // The multiplier of the cost of travelling through the area (1.0 for normal
+  // ground).
+  pub cost: f32,
+  // The polygon flags of polygons in the area (e.g. Detour's polygon flags,
+  // which filter path queries).
+  pub flags: u16,
+  // The colour used when debug drawing the area, as RGBA.
+  pub debug_color: [u8; 4],
+}
+
+// A table of the meanings of area IDs, so every system (marking, flag
+// assignment, path costs, debug drawing) can share a single definition of each
+// area. Serialized as a map from area ID to AreaInfo, which is validated the
+// same way as `AreaRegistry::insert` when deserializing.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(
+  feature = "serde",
+  derive(serde::Serialize, serde::Deserialize),
+  serde(try_from = "BTreeMap<u8, AreaInfo>", into = "BTreeMap<u8, AreaInfo>")
+)]
+pub struct AreaRegistry {
+  areas: BTreeMap<u8, AreaInfo>,
+}
+
+// The reason an area could not be registered in an AreaRegistry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AreaRegistryError {
+  // The area ID is `INVALID_AREA_ID` or greater than `WALKABLE_AREA_ID`.
+  InvalidAreaId(u8),
+  // Another area ID already has the same name.
+  DuplicateName(String),
+}
+
+impl fmt::Display for AreaRegistryError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      AreaRegistryError::InvalidAreaId(area_id) => write!(
+        f,
+        "Area ID must be in 1..={}. area_id={}",
+        WALKABLE_AREA_ID, area_id
+      ),
+      AreaRegistryError::DuplicateName(name) => {
+        write!(f, "Area name is already registered. name={}", name)
+      }
+    }
+  }
+}
+
+impl std::error::Error for AreaRegistryError {}
+
+impl AreaRegistry {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  // Registers `area` as the meaning of `area_id`, replacing any previous
+  // meaning. Panics if `area_id` is `INVALID_AREA_ID` or greater than
+  // `WALKABLE_AREA_ID` (Recast only stores 6 bits of area IDs), or if another
+  // area ID already has the same name.
+  pub fn insert(&mut self, area_id: u8, area: AreaInfo) {
+    if let Err(error) = self.try_insert(area_id, area) {
+      panic!("{}", error);
+    }
+  }
+
+  // Same as `insert`, but returns an error instead of panicking.
+  pub fn try_insert(
+    &mut self,
+    area_id: u8,
+    area: AreaInfo,
+  ) -> Result<(), AreaRegistryError> {
+    if area_id == INVALID_AREA_ID || area_id > WALKABLE_AREA_ID {
+      return Err(AreaRegistryError::InvalidAreaId(area_id));
+    }
+    if self
+      .area_id(&area.name)
+      .is_some_and(|existing_area_id| existing_area_id != area_id)
+    {
+      return Err(AreaRegistryError::DuplicateName(area.name));
+    }
+    self.areas.insert(area_id, area);
+    Ok(())
+  }
+
+  // Removes the meaning of `area_id`, returning it if it was registered.
+  pub fn remove(&mut self, area_id: u8) -> Option<AreaInfo> {
+    self.areas.remove(&area_id)
+  }
+
+  pub fn get(&self, area_id: u8) -> Option<&AreaInfo> {
+    self.areas.get(&area_id)
+  }
+
+  // Returns the area ID registered with `name`, e.g. to pass to marking
+  // functions.
+  pub fn area_id(&self, name: &str) -> Option<u8> {
+    self
+      .areas
+      .iter()
+      .find(|(_, area)| area.name == name)
+      .map(|(&area_id, _)| area_id)
+  }
+
+  // Creates an Iterator of all registered areas, ordered by area ID.
+  pub fn iter(&self) -> impl Iterator<Item = (u8, &AreaInfo)> + '_ {
+    self.areas.iter().map(|(&area_id, area)| (area_id, area))
+  }
+
+  // Returns the cost multiplier of `area_id`, or None if the area is not
+  // registered. This can be used as the `area_cost` of path queries (e.g.
+  // `CompactHeightfield::find_grid_path`), which makes unregistered areas
+  // impassable.
+  pub fn area_cost(&self, area_id: u8) -> Option<f32> {
+    self.get(area_id).map(|area| area.cost)
+  }
+
+  // Returns the polygon flags of `area_id`, or 0 if the area is not registered.
+  pub fn flags(&self, area_id: u8) -> u16 {
+    self.get(area_id).map_or(0, |area| area.flags)
+  }
+
+  // Returns the debug colour of `area_id`, or None if the area is not
+  // registered.
+  pub fn debug_color(&self, area_id: u8) -> Option<[u8; 4]> {
+    self.get(area_id).map(|area| area.debug_color)
+  }
+
+  // Sets the flags of every polygon in `poly_mesh` to the flags of its area.
+  pub fn assign_polygon_flags(&self, poly_mesh: &mut PolyMesh) {
+    for index in 0..poly_mesh.polygons_len() {
+      let flags = self.flags(poly_mesh.polygon(index).area_id());
+      poly_mesh.set_polygon_flags(index, flags);
+    }
+  }
+}
+
+impl TryFrom<BTreeMap<u8, AreaInfo>> for AreaRegistry {
+  type Error = AreaRegistryError;
+
+  // Registers each area, failing if any area could not be inserted.
+  fn try_from(areas: BTreeMap<u8, AreaInfo>) -> Result<Self, Self::Error> {
+    let mut registry = AreaRegistry::new();
+    for (area_id, area) in areas {
+      registry.try_insert(area_id, area)?;
+    }
+    Ok(registry)
+  }
+}
+
+impl From<AreaRegistry> for BTreeMap<u8, AreaInfo> {
+  fn from(registry: AreaRegistry) -> Self {
+    registry.areas
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{collections::BTreeMap, panic::AssertUnwindSafe};
+
+  use crate::{
+    test_util::floor_compact_heightfield, AreaInfo, AreaRegistry,
+    AreaRegistryError, Context, ContourBuildFlags, ContourSet, PolyMesh, Vec3,
+    INVALID_AREA_ID, WALKABLE_AREA_ID,
+  };
+
+  fn registry() -> AreaRegistry {
+    let mut registry = AreaRegistry::new();
+    registry.insert(
+      WALKABLE_AREA_ID,
+      AreaInfo {
+        name: "ground".into(),
+        cost: 1.0,
+        flags: 0b01,
+        debug_color: [0, 192, 255, 64],
+      },
+    );
+    registry.insert(
+      2,
+      AreaInfo {
+        name: "water".into(),
+        cost: 10.0,
+        flags: 0b10,
+        debug_color: [0, 0, 255, 128],
+      },
+    );
+    registry
+  }
+
+  #[test]
+  fn registers_areas() {
+    let mut registry = registry();
+
+    assert_eq!(registry.area_id("water"), Some(2));
+    assert_eq!(registry.area_id("lava"), None);
+    assert_eq!(registry.area_cost(2), Some(10.0));
+    assert_eq!(registry.area_cost(3), None);
+    assert_eq!(registry.flags(WALKABLE_AREA_ID), 0b01);
+    assert_eq!(registry.flags(3), 0);
+    assert_eq!(registry.debug_color(2), Some([0, 0, 255, 128]));
+    assert_eq!(
+      registry.iter().map(|(area_id, _)| area_id).collect::<Vec<_>>(),
+      [2, WALKABLE_AREA_ID]
+    );
+
+    let water = registry.remove(2).expect("water was registered");
+    assert_eq!(registry.get(2), None);
+
+    let mut renamed_water = water.clone();
+    renamed_water.name = "ground".into();
+    for (area_id, area) in [
+      (INVALID_AREA_ID, water.clone()),
+      (WALKABLE_AREA_ID + 1, water),
+      (2, renamed_water),
+    ] {
+      let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        registry.insert(area_id, area.clone());
+      }));
+      assert!(
+        result.is_err(),
+        "Expected invalid area to break an assert, but succeeded. area_id={}, area={:?}",
+        area_id,
+        area
+      );
+    }
+  }
+
+  #[test]
+  fn validates_area_tables() {
+    let areas = BTreeMap::from(registry());
+    assert_eq!(AreaRegistry::try_from(areas.clone()), Ok(registry()));
+
+    let mut invalid_areas = areas.clone();
+    invalid_areas.insert(INVALID_AREA_ID, invalid_areas[&2].clone());
+    assert_eq!(
+      AreaRegistry::try_from(invalid_areas),
+      Err(AreaRegistryError::InvalidAreaId(INVALID_AREA_ID))
+    );
+
+    let mut duplicate_areas = areas;
+    duplicate_areas.insert(3, duplicate_areas[&2].clone());
+    assert_eq!(
+      AreaRegistry::try_from(duplicate_areas),
+      Err(AreaRegistryError::DuplicateName("water".into()))
+    );
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn serializes_areas() {
+    let json =
+      serde_json::to_string(&registry()).expect("serializing succeeds");
+    assert_eq!(
+      serde_json::from_str::<AreaRegistry>(&json)
+        .expect("deserializing succeeds"),
+      registry()
+    );
+
+    let area_json =
+      serde_json::to_string(&registry().get(2)).expect("serializing succeeds");
+    for invalid_json in [
+      format!("{{\"0\":{}}}", area_json),
+      format!("{{\"64\":{}}}", area_json),
+      format!("{{\"2\":{},\"3\":{}}}", area_json, area_json),
+    ] {
+      assert!(
+        serde_json::from_str::<AreaRegistry>(&invalid_json).is_err(),
+        "Expected invalid registry to fail deserializing. json={}",
+        invalid_json
+      );
+    }
+  }
+
+  #[test]
+  fn assigns_polygon_flags() {
+    let registry = registry();
+    let mut context = Context::new();
+
+    // Water covers the half of the floor with X above 5.
+    let water = registry.area_id("water").expect("water is registered");
+    let mut compact_heightfield =
+      floor_compact_heightfield(&mut context, Vec3::new(10.0, 5.0, 5.0));
+    compact_heightfield.mark_box_area_with_id(
+      &mut context,
+      Vec3::new(5.0, 0.0, 0.0),
+      Vec3::new(10.0, 1.0, 5.0),
+      water,
+    );
+
+    let compact_heightfield = compact_heightfield
+      .build_regions(&mut context, 0, 1, 1)
+      .expect("regions built");
+    let contour_set = ContourSet::new(
+      &compact_heightfield,
+      &mut context,
+      1.0,
+      10,
+      ContourBuildFlags {
+        tessellate_wall_edges: true,
+        tessellate_area_edges: false,
+      },
+    )
+    .expect("contours built");
+    let mut poly_mesh =
+      PolyMesh::new(&contour_set, &mut context, 6).expect("poly mesh built");
+
+    registry.assign_polygon_flags(&mut poly_mesh);
+
+    let flags_by_area = poly_mesh
+      .polygons_iter()
+      .map(|polygon| (polygon.area_id(), polygon.flags()))
+      .collect::<Vec<_>>();
+    assert!(flags_by_area.contains(&(WALKABLE_AREA_ID, 0b01)));
+    assert!(flags_by_area.contains(&(water, 0b10)));
+    for (area_id, flags) in flags_by_area {
+      assert_eq!(flags, registry.flags(area_id));
+    }
+  }
+}
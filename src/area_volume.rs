@@ -0,0 +1,67 @@
+use crate::Vec3;
+
+// The shape of an AreaVolume. Each shape marks exactly the same spans as the
+// corresponding `CompactHeightfield::mark_*_area_with_id` function.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AreaVolumeShape {
+  // See `CompactHeightfield::mark_box_area_with_id`.
+  Box {
+    min_bounds: Vec3<f32>,
+    max_bounds: Vec3<f32>,
+  },
+  // See `CompactHeightfield::mark_cylinder_area_with_id`.
+  Cylinder {
+    position: Vec3<f32>,
+    radius: f32,
+    height: f32,
+  },
+  // See `CompactHeightfield::mark_convex_poly_area_with_id`. If `offset` is
+  // provided, see `CompactHeightfield::mark_offset_convex_poly_area_with_id`.
+  ConvexPoly {
+    vertices: Vec<Vec3<f32>>,
+    offset: Option<f32>,
+    base_height: f32,
+    top_height: f32,
+  },
+}
+
+// A volume to mark with an area ID. See
+// `CompactHeightfield::mark_area_volumes`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AreaVolume {
+  pub shape: AreaVolumeShape,
+  // The area ID to mark spans in the volume with.
+  pub area_id: u8,
+  // Where volumes overlap, the volume with the highest priority determines the
+  // area ID.
+  pub priority: i32,
+}
+
+// Returns whether `point` is inside the polygon defined by `vertices` on the XZ
+// plane. This matches the test used by Recast to mark convex polygons.
+pub(crate) fn point_in_polygon(
+  vertices: &[Vec3<f32>],
+  point: Vec3<f32>,
+) -> bool {
+  let mut in_polygon = false;
+  let mut j = vertices.len().wrapping_sub(1);
+  for (i, vertex_i) in vertices.iter().enumerate() {
+    let vertex_j = &vertices[j];
+    j = i;
+
+    if (vertex_i.z > point.z) == (vertex_j.z > point.z) {
+      continue;
+    }
+
+    if point.x
+      >= (vertex_j.x - vertex_i.x) * (point.z - vertex_i.z)
+        / (vertex_j.z - vertex_i.z)
+        + vertex_i.x
+    {
+      continue;
+    }
+
+    in_polygon = !in_polygon;
+  }
+  in_polygon
+}
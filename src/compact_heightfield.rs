@@ -4,10 +4,14 @@ use recastnavigation_sys::{
   rcBuildCompactHeightfield, rcBuildDistanceField, rcBuildLayerRegions,
   rcBuildRegions, rcBuildRegionsMonotone, rcCompactSpan, rcErodeWalkableArea,
   rcMarkBoxArea, rcMarkConvexPolyArea, rcMarkCylinderArea,
-  rcMedianFilterWalkableArea,
+  rcMedianFilterWalkableArea, RC_BORDER_REG, RC_NOT_CONNECTED,
 };
 
-use crate::{wrappers, Context, Heightfield, Vec3};
+use crate::{
+  area_volume, grid_path, region_info, sampling, util, wrappers, AreaVolume,
+  AreaVolumeShape, Context, Floor, GridPath, Heightfield, RegionInfo,
+  SampleMode, Vec3, INVALID_AREA_ID,
+};
 
 // A Recast CompactHeightfield. This is generally created from a Heightfield and
 // represents the non-solid areas of the world.
@@ -29,6 +33,47 @@ pub trait CompactHeightfieldState {}
 impl CompactHeightfieldState for NoRegions {}
 impl CompactHeightfieldState for HasRegions {}
 
+// How `CompactHeightfield::filter_area_ids` chooses the new area ID of a span
+// from the area IDs in its kernel.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AreaFilterMode {
+  // Use the median area ID (this is what `median_filter_area_ids` does).
+  Median,
+  // Use the most common area ID. Ties keep the span's own area ID if it is
+  // one of the most common, otherwise the lowest area ID wins.
+  Majority,
+}
+
+// Options for `CompactHeightfield::filter_area_ids`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AreaFilterOptions {
+  // The number of cells around each span to consider, so the kernel is
+  // (2 * `kernel_radius` + 1) cells wide. A radius of 1 matches
+  // `median_filter_area_ids`.
+  pub kernel_radius: u32,
+  pub mode: AreaFilterMode,
+  // Area IDs that are never overwritten or introduced by the filter, e.g. to
+  // keep thin areas like narrow bridges intact.
+  pub preserved_area_ids: Vec<u8>,
+}
+
+// The algorithm used to partition a CompactHeightfield into regions. See
+// `CompactHeightfield::partition`. `border_size` is the size of the
+// non-navigable border around the heightfield. `min_region_area` is the minimum
+// number of cells allowed to form an isolated island. Any regions with fewer
+// span counts than `merge_region_area` will prefer to be merged into a larger
+// region.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PartitionStrategy {
+  // See `CompactHeightfield::build_regions`.
+  Watershed { border_size: i32, min_region_area: i32, merge_region_area: i32 },
+  // See `CompactHeightfield::build_regions_monotone`.
+  Monotone { border_size: i32, min_region_area: i32, merge_region_area: i32 },
+  // See `CompactHeightfield::build_layer_regions`.
+  Layers { border_size: i32, min_region_area: i32 },
+}
+
 impl<TypeState: CompactHeightfieldState> CompactHeightfield<TypeState> {
   pub fn grid_width(&self) -> i32 {
     self.compact_heightfield.width
@@ -124,6 +169,20 @@ impl<TypeState: CompactHeightfieldState> CompactHeightfield<TypeState> {
     raw_spans.iter().map(|span| CompactSpan { compact_heightfield: self, span })
   }
 
+  // Returns the span at `index`.
+  pub fn span(&self, index: usize) -> CompactSpan<'_, TypeState> {
+    // SAFETY: `spans` is guaranteed to have `spanCount` elements, and be well
+    // aligned.
+    let raw_spans = unsafe {
+      std::slice::from_raw_parts(
+        self.compact_heightfield.spans,
+        self.spans_len(),
+      )
+    };
+
+    CompactSpan { compact_heightfield: self, span: &raw_spans[index] }
+  }
+
   // Returns a slice of the area IDs of each span.
   pub fn span_areas(&self) -> &[u8] {
     // SAFETY: `areas` is guaranteed to have `spanCount` elements, and be well
@@ -135,6 +194,112 @@ impl<TypeState: CompactHeightfieldState> CompactHeightfield<TypeState> {
       )
     }
   }
+
+  // Returns the walkable floors (spans that aren't `INVALID_AREA_ID`) in the
+  // cell containing the world position `x` and `z`, ordered from lowest to
+  // highest. Heights are determined by `sample_mode`; the clearance and area ID
+  // are always taken from the containing cell. Returns nothing if the position
+  // is outside the grid.
+  pub fn floors_at(
+    &self,
+    x: f32,
+    z: f32,
+    sample_mode: SampleMode,
+  ) -> Vec<Floor> {
+    let min_bounds = self.min_bounds();
+    let cell_horizontal_size = self.cell_horizontal_size();
+    let Some((cell_x, cell_z)) = sampling::containing_cell(
+      min_bounds,
+      cell_horizontal_size,
+      self.grid_width(),
+      self.grid_height(),
+      x,
+      z,
+    ) else {
+      return Vec::new();
+    };
+
+    let areas = self.span_areas();
+    let column_heights = |column_x: i32, column_z: i32| {
+      let cell = if 0 <= column_x
+        && column_x < self.grid_width()
+        && 0 <= column_z
+        && column_z < self.grid_height()
+      {
+        self.cell((column_x + column_z * self.grid_width()) as usize)
+      } else {
+        0..0
+      };
+      cell
+        .filter(|&span_index| areas[span_index] != INVALID_AREA_ID)
+        .map(|span_index| self.span(span_index).y_start_f32())
+    };
+
+    self
+      .cell((cell_x + cell_z * self.grid_width()) as usize)
+      .filter(|&span_index| areas[span_index] != INVALID_AREA_ID)
+      .map(|span_index| {
+        let span = self.span(span_index);
+        Floor {
+          span_index,
+          height: sampling::sample_height(
+            sample_mode,
+            min_bounds,
+            cell_horizontal_size,
+            x,
+            z,
+            span.y_start_f32(),
+            column_heights,
+          ),
+          clearance: span.y_size_f32(),
+          area_id: areas[span_index],
+        }
+      })
+      .collect()
+  }
+
+  // Finds the cheapest path between the floors closest to `start` and `end`
+  // by moving between connected spans (including diagonally). This is useful
+  // for prototyping, or as a reference to compare navigation mesh paths
+  // against. `area_cost` returns the cost of travelling one world unit in an
+  // area, or None if the area is impassable (`INVALID_AREA_ID` is always
+  // impassable). Costs should be at least 1 for the path to be the cheapest.
+  // Returns None if either end has no passable floor or they are not
+  // connected.
+  pub fn find_grid_path(
+    &self,
+    start: Vec3<f32>,
+    end: Vec3<f32>,
+    area_cost: impl Fn(u8) -> Option<f32>,
+  ) -> Option<GridPath> {
+    grid_path::find_grid_path(self, start, end, area_cost)
+  }
+
+  // Returns the index of the span connected to the span at `span_index` in
+  // `direction`, or None if there is no connected span. `x` and `z` are the
+  // grid coordinates of the cell containing `span_index`.
+  pub(crate) fn neighbour_span_index(
+    &self,
+    x: i32,
+    z: i32,
+    span_index: usize,
+    direction: Direction,
+  ) -> Option<usize> {
+    let (delta_x, delta_z) = match direction {
+      Direction::NegX => (-1, 0),
+      Direction::PosZ => (0, 1),
+      Direction::PosX => (1, 0),
+      Direction::NegZ => (0, -1),
+    };
+
+    let connection = self.span(span_index).connection(direction);
+    if connection == RC_NOT_CONNECTED as u32 {
+      return None;
+    }
+
+    let neighbour_cell = (x + delta_x) + (z + delta_z) * self.grid_width();
+    Some(self.cell(neighbour_cell as usize).start + connection as usize)
+  }
 }
 
 impl CompactHeightfield<NoRegions> {
@@ -191,6 +356,110 @@ impl CompactHeightfield<NoRegions> {
     }
   }
 
+  // Erodes the walkable area by a radius (in grid cells) that depends on each
+  // span's area ID. `erosion_radius` returns the radius for an area ID. This
+  // matches `erode_walkable_area` when `erosion_radius` is constant: spans
+  // closer than their radius to an unwalkable span or the edge of the walkable
+  // area are marked as `INVALID_AREA_ID`.
+  pub fn erode_walkable_area_per_area(
+    &mut self,
+    erosion_radius: impl Fn(u8) -> i32,
+  ) {
+    let distances = self.distances_to_boundary();
+    for (area, distance) in self.span_areas_mut().iter_mut().zip(distances) {
+      if *area == INVALID_AREA_ID {
+        continue;
+      }
+
+      let min_distance = (erosion_radius(*area) * 2).clamp(0, 255);
+      if (distance as i32) < min_distance {
+        *area = INVALID_AREA_ID;
+      }
+    }
+  }
+
+  // Computes the distance of each span to the nearest unwalkable span or edge
+  // of the walkable area, the same way as `erode_walkable_area`. Distances are
+  // in half-cells (i.e. a value of 2 is one cell away) and saturate at 255.
+  fn distances_to_boundary(&self) -> Vec<u8> {
+    let areas = self.span_areas();
+    let neighbour = |x: i32, z: i32, span_index: usize, direction_index| {
+      let direction =
+        [Direction::NegX, Direction::PosZ, Direction::PosX, Direction::NegZ]
+          [direction_index];
+      let (delta_x, delta_z) =
+        [(-1, 0), (0, 1), (1, 0), (0, -1)][direction_index];
+      self
+        .neighbour_span_index(x, z, span_index, direction)
+        .map(|neighbour_index| (x + delta_x, z + delta_z, neighbour_index))
+    };
+
+    // Mark boundary spans.
+    let mut distances = vec![u8::MAX; self.spans_len()];
+    for (cell_index, cell) in self.cells_iter().enumerate() {
+      let x = cell_index as i32 % self.grid_width();
+      let z = cell_index as i32 / self.grid_width();
+      for span_index in cell {
+        let is_boundary = areas[span_index] == INVALID_AREA_ID
+          || (0..4).any(|direction_index| {
+            match neighbour(x, z, span_index, direction_index) {
+              None => true,
+              Some((_, _, neighbour_index)) => {
+                areas[neighbour_index] == INVALID_AREA_ID
+              }
+            }
+          });
+        if is_boundary {
+          distances[span_index] = 0;
+        }
+      }
+    }
+
+    // Each pass propagates distances from an orthogonal neighbour (cost 2),
+    // and the diagonal neighbour reached from it (cost 3). The first pass
+    // sweeps forwards using the -X and -Z neighbours, and the second pass
+    // sweeps backwards using the +X and +Z neighbours.
+    let mut relax =
+      |x: i32, z: i32, span_index: usize, passes: [(usize, usize); 2]| {
+        for (direction_index, diagonal_direction_index) in passes {
+          let Some((neighbour_x, neighbour_z, neighbour_index)) =
+            neighbour(x, z, span_index, direction_index)
+          else {
+            continue;
+          };
+          distances[span_index] = distances[span_index]
+            .min(distances[neighbour_index].saturating_add(2));
+
+          if let Some((_, _, diagonal_index)) = neighbour(
+            neighbour_x,
+            neighbour_z,
+            neighbour_index,
+            diagonal_direction_index,
+          ) {
+            distances[span_index] = distances[span_index]
+              .min(distances[diagonal_index].saturating_add(3));
+          }
+        }
+      };
+
+    for z in 0..self.grid_height() {
+      for x in 0..self.grid_width() {
+        for span_index in self.cell((x + z * self.grid_width()) as usize) {
+          relax(x, z, span_index, [(0, 3), (3, 2)]);
+        }
+      }
+    }
+    for z in (0..self.grid_height()).rev() {
+      for x in (0..self.grid_width()).rev() {
+        for span_index in self.cell((x + z * self.grid_width()) as usize) {
+          relax(x, z, span_index, [(2, 1), (1, 0)]);
+        }
+      }
+    }
+
+    distances
+  }
+
   // Marks all spans in the specified box with the area ID of `new_id`.
   pub fn mark_box_area_with_id(
     &mut self,
@@ -249,7 +518,7 @@ impl CompactHeightfield<NoRegions> {
     top_height: f32,
     new_id: u8,
   ) {
-    // SAFETY: `rcMarkCylinderArea` only mutates `context.context` and
+    // SAFETY: `rcMarkConvexPolyArea` only mutates `context.context` and
     // `self.compact_heightfield`, which are exclusively borrowed. `vertices`
     // are only read.
     unsafe {
@@ -265,6 +534,212 @@ impl CompactHeightfield<NoRegions> {
     };
   }
 
+  // Same as `mark_convex_poly_area_with_id`, but the polygon is first expanded
+  // by `offset` (usually the agent radius). See `util::offset_polygon` for
+  // details.
+  pub fn mark_offset_convex_poly_area_with_id(
+    &mut self,
+    context: &mut Context,
+    vertices: &[Vec3<f32>],
+    offset: f32,
+    base_height: f32,
+    top_height: f32,
+    new_id: u8,
+  ) {
+    self.mark_convex_poly_area_with_id(
+      context,
+      &util::offset_polygon(vertices, offset),
+      base_height,
+      top_height,
+      new_id,
+    );
+  }
+
+  // Marks all spans in each of `volumes` with the volume's area ID. Unlike
+  // calling the `mark_*_area_with_id` functions one after another, the result
+  // does not depend on the order of `volumes`: where volumes overlap, the
+  // volume with the highest priority wins (ties go to the highest area ID). If
+  // `preserve_invalid_areas` is true, volumes with `INVALID_AREA_ID` win
+  // regardless of priority, so no other volume can overwrite them. Like the
+  // other mark functions, spans that are already `INVALID_AREA_ID` are never
+  // marked.
+  pub fn mark_area_volumes(
+    &mut self,
+    volumes: &[AreaVolume],
+    preserve_invalid_areas: bool,
+  ) {
+    let priority_key = |volume: &AreaVolume| {
+      (
+        preserve_invalid_areas && volume.area_id == INVALID_AREA_ID,
+        volume.priority,
+        volume.area_id,
+      )
+    };
+
+    let mut winning_volumes: Vec<Option<&AreaVolume>> =
+      vec![None; self.spans_len()];
+    for volume in volumes {
+      for span_index in self.area_volume_span_indices(&volume.shape) {
+        let winning_volume = &mut winning_volumes[span_index];
+        let wins = match winning_volume {
+          None => true,
+          Some(winning_volume) => {
+            priority_key(volume) > priority_key(winning_volume)
+          }
+        };
+        if wins {
+          *winning_volume = Some(volume);
+        }
+      }
+    }
+
+    for (area, winning_volume) in
+      self.span_areas_mut().iter_mut().zip(winning_volumes)
+    {
+      if let Some(winning_volume) = winning_volume {
+        *area = winning_volume.area_id;
+      }
+    }
+  }
+
+  // Returns the indices of the spans that the corresponding
+  // `mark_*_area_with_id` function would mark for `shape`.
+  fn area_volume_span_indices(&self, shape: &AreaVolumeShape) -> Vec<usize> {
+    match shape {
+      AreaVolumeShape::Box { min_bounds, max_bounds } => {
+        self.footprint_span_indices(*min_bounds, *max_bounds, |_| true)
+      }
+      AreaVolumeShape::Cylinder { position, radius, height } => {
+        let radius_squared = radius * radius;
+        self.footprint_span_indices(
+          Vec3::new(position.x - radius, position.y, position.z - radius),
+          Vec3::new(
+            position.x + radius,
+            position.y + height,
+            position.z + radius,
+          ),
+          |cell_center| {
+            let delta_x = cell_center.x - position.x;
+            let delta_z = cell_center.z - position.z;
+            delta_x * delta_x + delta_z * delta_z < radius_squared
+          },
+        )
+      }
+      AreaVolumeShape::ConvexPoly {
+        vertices,
+        offset,
+        base_height,
+        top_height,
+      } => {
+        let offset_vertices;
+        let vertices = match offset {
+          Some(offset) => {
+            offset_vertices = util::offset_polygon(vertices, *offset);
+            &offset_vertices[..]
+          }
+          None => &vertices[..],
+        };
+
+        let Some(first_vertex) = vertices.first() else {
+          return Vec::new();
+        };
+
+        let mut min_bounds = *first_vertex;
+        let mut max_bounds = *first_vertex;
+        for vertex in vertices {
+          min_bounds.x = min_bounds.x.min(vertex.x);
+          min_bounds.z = min_bounds.z.min(vertex.z);
+          max_bounds.x = max_bounds.x.max(vertex.x);
+          max_bounds.z = max_bounds.z.max(vertex.z);
+        }
+        min_bounds.y = *base_height;
+        max_bounds.y = *top_height;
+
+        self.footprint_span_indices(min_bounds, max_bounds, |cell_center| {
+          area_volume::point_in_polygon(vertices, cell_center)
+        })
+      }
+    }
+  }
+
+  // Returns the indices of the spans that are not `INVALID_AREA_ID`, whose
+  // cells are covered by `min_bounds` and `max_bounds`, and whose bottom is
+  // within the vertical range of the bounds. Each cell is also only included if
+  // `contains_cell` returns true for the cell's center (with a Y of 0).
+  fn footprint_span_indices(
+    &self,
+    min_bounds: Vec3<f32>,
+    max_bounds: Vec3<f32>,
+    contains_cell: impl Fn(Vec3<f32>) -> bool,
+  ) -> Vec<usize> {
+    let heightfield_min_bounds = self.min_bounds();
+    let cell_horizontal_size = self.cell_horizontal_size();
+    let cell_height = self.cell_height();
+    let to_grid = |bounds: Vec3<f32>| {
+      Vec3::new(
+        ((bounds.x - heightfield_min_bounds.x) / cell_horizontal_size) as i32,
+        ((bounds.y - heightfield_min_bounds.y) / cell_height) as i32,
+        ((bounds.z - heightfield_min_bounds.z) / cell_horizontal_size) as i32,
+      )
+    };
+
+    let grid_min = to_grid(min_bounds);
+    let grid_max = to_grid(max_bounds);
+    if grid_max.x < 0
+      || grid_min.x >= self.grid_width()
+      || grid_max.z < 0
+      || grid_min.z >= self.grid_height()
+    {
+      return Vec::new();
+    }
+
+    // SAFETY: `spans` is guaranteed to have `spanCount` elements, and be well
+    // aligned.
+    let raw_spans = unsafe {
+      std::slice::from_raw_parts(
+        self.compact_heightfield.spans,
+        self.spans_len(),
+      )
+    };
+    let areas = self.span_areas();
+
+    let mut span_indices = Vec::new();
+    for z in grid_min.z.max(0)..=grid_max.z.min(self.grid_height() - 1) {
+      for x in grid_min.x.max(0)..=grid_max.x.min(self.grid_width() - 1) {
+        let cell_center = Vec3::new(
+          heightfield_min_bounds.x + (x as f32 + 0.5) * cell_horizontal_size,
+          0.0,
+          heightfield_min_bounds.z + (z as f32 + 0.5) * cell_horizontal_size,
+        );
+        if !contains_cell(cell_center) {
+          continue;
+        }
+
+        span_indices.extend(
+          self.cell((x + z * self.grid_width()) as usize).filter(|&index| {
+            let y = raw_spans[index].y as i32;
+            areas[index] != INVALID_AREA_ID
+              && grid_min.y <= y
+              && y <= grid_max.y
+          }),
+        );
+      }
+    }
+    span_indices
+  }
+
+  // Returns a mutable slice of the area IDs of each span.
+  fn span_areas_mut(&mut self) -> &mut [u8] {
+    // SAFETY: `areas` is guaranteed to have `spanCount` elements, and be well
+    // aligned. `self` is exclusively borrowed, so the slice is exclusive.
+    unsafe {
+      std::slice::from_raw_parts_mut(
+        self.compact_heightfield.areas,
+        self.spans_len(),
+      )
+    }
+  }
+
   // Performs a median filter on the area IDs of spans. This acts like a "blur"
   // which can remove noise from small unwalkable obstacles (e.g. a pebble
   // marked as unwalkable).
@@ -288,6 +763,132 @@ impl CompactHeightfield<NoRegions> {
     }
   }
 
+  // Filters the area IDs of spans like `median_filter_area_ids`, but with
+  // control over the kernel size, the voting method, and which area IDs may be
+  // changed. See `AreaFilterOptions`. Spans with `INVALID_AREA_ID` are never
+  // changed.
+  pub fn filter_area_ids(&mut self, options: &AreaFilterOptions) {
+    let areas = self.span_areas();
+    let kernel_radius =
+      i32::try_from(options.kernel_radius).expect("kernel_radius fits in i32");
+    let kernel_width = (kernel_radius * 2 + 1) as usize;
+    let kernel_len = kernel_width * kernel_width;
+
+    let mut new_areas = areas.to_vec();
+    for (cell_index, cell) in self.cells_iter().enumerate() {
+      let x = cell_index as i32 % self.grid_width();
+      let z = cell_index as i32 / self.grid_width();
+      for span_index in cell {
+        let area = areas[span_index];
+        if area == INVALID_AREA_ID || options.preserved_area_ids.contains(&area)
+        {
+          continue;
+        }
+
+        // Positions in the kernel without a walkable span count as the span's
+        // own area.
+        let mut kernel_areas = self
+          .kernel_span_indices(x, z, span_index, kernel_radius)
+          .into_iter()
+          .map(|kernel_index| areas[kernel_index])
+          .filter(|&kernel_area| kernel_area != INVALID_AREA_ID)
+          .collect::<Vec<_>>();
+        kernel_areas.resize(kernel_len, area);
+        kernel_areas.sort_unstable();
+
+        let new_area = match options.mode {
+          AreaFilterMode::Median => kernel_areas[kernel_len / 2],
+          AreaFilterMode::Majority => {
+            let mut majority_area = area;
+            let mut majority_count = kernel_areas
+              .iter()
+              .filter(|&&kernel_area| kernel_area == area)
+              .count();
+            // The areas are sorted, so equal areas form runs.
+            let mut run_start = 0;
+            while run_start < kernel_areas.len() {
+              let run_area = kernel_areas[run_start];
+              let run_len = kernel_areas[run_start..]
+                .iter()
+                .take_while(|&&kernel_area| kernel_area == run_area)
+                .count();
+              if run_len > majority_count {
+                majority_area = run_area;
+                majority_count = run_len;
+              }
+              run_start += run_len;
+            }
+            majority_area
+          }
+        };
+
+        if !options.preserved_area_ids.contains(&new_area) {
+          new_areas[span_index] = new_area;
+        }
+      }
+    }
+
+    self.span_areas_mut().copy_from_slice(&new_areas);
+  }
+
+  // Returns the spans in the (2 * `radius` + 1)^2 cells centred on
+  // `span_index` (at grid coordinates `x` and `z`) which are reachable by
+  // following connections within those cells. At most one span is returned
+  // per cell. `radius` must not be negative.
+  fn kernel_span_indices(
+    &self,
+    x: i32,
+    z: i32,
+    span_index: usize,
+    radius: i32,
+  ) -> Vec<usize> {
+    let directions =
+      [Direction::NegX, Direction::PosZ, Direction::PosX, Direction::NegZ];
+    let offsets = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+    let kernel_width = (radius * 2 + 1) as usize;
+    let mut visited_cells = vec![false; kernel_width * kernel_width];
+    let kernel_cell = |delta_x: i32, delta_z: i32| {
+      (delta_x + radius) as usize + (delta_z + radius) as usize * kernel_width
+    };
+
+    // Breadth-first search from `span_index`, storing each span with the
+    // grid coordinates of its cell.
+    let mut kernel_spans = vec![(span_index, x, z)];
+    visited_cells[kernel_cell(0, 0)] = true;
+    let mut next = 0;
+    while next < kernel_spans.len() {
+      let (current_index, current_x, current_z) = kernel_spans[next];
+      next += 1;
+
+      for (&direction, &(offset_x, offset_z)) in directions.iter().zip(&offsets)
+      {
+        let (neighbour_x, neighbour_z) =
+          (current_x + offset_x, current_z + offset_z);
+        let (delta_x, delta_z) = (neighbour_x - x, neighbour_z - z);
+        if delta_x.abs() > radius
+          || delta_z.abs() > radius
+          || visited_cells[kernel_cell(delta_x, delta_z)]
+        {
+          continue;
+        }
+
+        let Some(neighbour_index) = self.neighbour_span_index(
+          current_x,
+          current_z,
+          current_index,
+          direction,
+        ) else {
+          continue;
+        };
+        visited_cells[kernel_cell(delta_x, delta_z)] = true;
+        kernel_spans.push((neighbour_index, neighbour_x, neighbour_z));
+      }
+    }
+
+    kernel_spans.into_iter().map(|(kernel_index, _, _)| kernel_index).collect()
+  }
+
   fn build_distance_field(&mut self, context: &mut Context) -> Result<(), ()> {
     // SAFETY: rcBuildDistanceField only mutates `context.context`, or
     // `self.compact_heightfield`.
@@ -414,9 +1015,160 @@ impl CompactHeightfield<NoRegions> {
       Err(())
     }
   }
+
+  // Builds regions using the algorithm specified by `strategy`. This makes the
+  // CompactHeightfield immutable (as modifying the underlying data could make
+  // the region data inconsistent).
+  pub fn partition(
+    self,
+    context: &mut Context,
+    strategy: PartitionStrategy,
+  ) -> Result<CompactHeightfield<HasRegions>, ()> {
+    match strategy {
+      PartitionStrategy::Watershed {
+        border_size,
+        min_region_area,
+        merge_region_area,
+      } => self.build_regions(
+        context,
+        border_size,
+        min_region_area,
+        merge_region_area,
+      ),
+      PartitionStrategy::Monotone {
+        border_size,
+        min_region_area,
+        merge_region_area,
+      } => self.build_regions_monotone(
+        context,
+        border_size,
+        min_region_area,
+        merge_region_area,
+      ),
+      PartitionStrategy::Layers { border_size, min_region_area } => {
+        self.build_layer_regions(context, border_size, min_region_area)
+      }
+    }
+  }
+
+  // Assigns each span the region ID at the same index in `region_ids`, rather
+  // than building regions from the heightfield. This makes the
+  // CompactHeightfield immutable (as modifying the underlying data could make
+  // the region data inconsistent). A region ID of 0 means the span is not part
+  // of any region, and spans with `INVALID_AREA_ID` must have a region ID of 0.
+  // Region IDs may not use the border region flag (0x8000). Fails if any of
+  // these conditions are violated, or if `region_ids` does not have an entry
+  // for each span.
+  pub fn build_regions_from_ids(
+    mut self,
+    region_ids: &[u16],
+  ) -> Result<CompactHeightfield<HasRegions>, ()> {
+    if region_ids.len() != self.spans_len() {
+      return Err(());
+    }
+
+    for (&region_id, &area) in region_ids.iter().zip(self.span_areas()) {
+      if region_id & RC_BORDER_REG != 0
+        || (area == INVALID_AREA_ID && region_id != 0)
+      {
+        return Err(());
+      }
+    }
+
+    // SAFETY: `spans` is guaranteed to have `spanCount` elements, and be well
+    // aligned. `self` is owned, so the slice is exclusive.
+    let raw_spans = unsafe {
+      std::slice::from_raw_parts_mut(
+        self.compact_heightfield.spans,
+        self.spans_len(),
+      )
+    };
+    for (raw_span, &region_id) in raw_spans.iter_mut().zip(region_ids) {
+      raw_span.reg = region_id;
+    }
+
+    self.compact_heightfield.maxRegions =
+      region_ids.iter().copied().max().unwrap_or(0) + 1;
+    self.compact_heightfield.borderSize = 0;
+
+    Ok(CompactHeightfield::<HasRegions> {
+      compact_heightfield: self.compact_heightfield,
+      marker: std::marker::PhantomData,
+    })
+  }
+
+  // Same as `build_regions_from_ids`, but the region ID of each span is
+  // determined by calling `region_id_fn` with the index of the span and the
+  // world position of the center of the span's floor. `region_id_fn` is not
+  // called for spans with `INVALID_AREA_ID` (these are given a region ID of 0).
+  pub fn build_regions_with(
+    self,
+    mut region_id_fn: impl FnMut(usize, Vec3<f32>) -> u16,
+  ) -> Result<CompactHeightfield<HasRegions>, ()> {
+    let min_bounds = self.min_bounds();
+    let cell_horizontal_size = self.cell_horizontal_size();
+    let floors =
+      self.spans_iter().map(|span| span.y_start_f32()).collect::<Vec<_>>();
+    let areas = self.span_areas();
+
+    let mut region_ids = vec![0; self.spans_len()];
+    for (cell_index, cell) in self.cells_iter().enumerate() {
+      let x = cell_index as i32 % self.grid_width();
+      let z = cell_index as i32 / self.grid_width();
+      for span_index in cell {
+        if areas[span_index] == INVALID_AREA_ID {
+          continue;
+        }
+
+        let position = Vec3::new(
+          min_bounds.x + (x as f32 + 0.5) * cell_horizontal_size,
+          floors[span_index],
+          min_bounds.z + (z as f32 + 0.5) * cell_horizontal_size,
+        );
+        region_ids[span_index] = region_id_fn(span_index, position);
+      }
+    }
+
+    self.build_regions_from_ids(&region_ids)
+  }
 }
 
 impl CompactHeightfield<HasRegions> {
+  // Discards the region data and the distance field, making the
+  // CompactHeightfield mutable again. This allows modifying areas and
+  // rebuilding regions without rebuilding the CompactHeightfield from scratch.
+  pub fn clear_regions(mut self) -> CompactHeightfield<NoRegions> {
+    // SAFETY: `spans` is guaranteed to have `spanCount` elements, and be well
+    // aligned. `self` is owned, so the slice is exclusive.
+    let raw_spans = unsafe {
+      std::slice::from_raw_parts_mut(
+        self.compact_heightfield.spans,
+        self.spans_len(),
+      )
+    };
+    for raw_span in raw_spans {
+      raw_span.reg = 0;
+    }
+
+    self.compact_heightfield.maxRegions = 0;
+    self.compact_heightfield.borderSize = 0;
+
+    // The distance field is derived from the areas, so it would be stale once
+    // the areas are modified. Recast only exposes rcFree through the compact
+    // heightfield's destructor, so hand `dist` to an empty compact heightfield
+    // to release it. If that allocation fails, `dist` is leaked instead.
+    if let Ok(mut dist_owner) = wrappers::RawCompactHeightfield::new() {
+      dist_owner.dist = self.compact_heightfield.dist;
+    }
+    self.compact_heightfield.dist = std::ptr::null_mut();
+    self.compact_heightfield.maxDistance = 0;
+
+    CompactHeightfield::<NoRegions> {
+      compact_heightfield: self.compact_heightfield,
+      marker: std::marker::PhantomData,
+    }
+  }
+
   pub fn border_size(&self) -> i32 {
     self.compact_heightfield.borderSize
   }
@@ -425,9 +1177,57 @@ impl CompactHeightfield<HasRegions> {
     self.compact_heightfield.maxRegions
   }
 
+  // Returns the maximum value in `span_distances`.
   pub fn max_distance(&self) -> u16 {
     self.compact_heightfield.maxDistance
   }
+
+  // Returns a slice of the distance of each span to the nearest boundary (the
+  // edge of the heightfield, or a change in area ID). This is the distance
+  // field used as the input to watershed partitioning. Distances are
+  // approximate, and measured in half-cells (i.e. a value of 2 is one cell
+  // away from the boundary). See `span_distance_cells` and
+  // `span_distance_world` for more convenient units.
+  pub fn span_distances(&self) -> &[u16] {
+    if self.compact_heightfield.dist.is_null() {
+      return &[];
+    }
+
+    // SAFETY: `dist` is not null, so it is guaranteed to have `spanCount`
+    // elements, and be well aligned.
+    unsafe {
+      std::slice::from_raw_parts(
+        self.compact_heightfield.dist,
+        self.spans_len(),
+      )
+    }
+  }
+
+  // Returns the distance of the span at `index` to the nearest boundary in
+  // cells, or None if the regions were not built from a distance field (e.g.
+  // by `build_regions_from_ids`). See `span_distances`.
+  pub fn span_distance_cells(&self, index: usize) -> Option<f32> {
+    if self.compact_heightfield.dist.is_null() {
+      return None;
+    }
+    Some(self.span_distances()[index] as f32 * 0.5)
+  }
+
+  // Returns the distance of the span at `index` to the nearest boundary in
+  // world units, or None if the regions were not built from a distance field.
+  // See `span_distance_cells`.
+  pub fn span_distance_world(&self, index: usize) -> Option<f32> {
+    self
+      .span_distance_cells(index)
+      .map(|distance| distance * self.cell_horizontal_size())
+  }
+
+  // Computes statistics about each region (e.g. span count, bounds, and which
+  // regions it touches). Regions are ordered by region ID. Spans with no
+  // region or in the border region are not included.
+  pub fn region_infos(&self) -> Vec<RegionInfo> {
+    region_info::build_region_infos(self)
+  }
 }
 
 // A single span in a CompactHeightfield. A span represents a vertical column of
@@ -541,8 +1341,13 @@ pub enum Direction {
 #[cfg(test)]
 mod tests {
   use crate::{
-    CompactHeightfield, Context, HasRegions, Heightfield, NoRegions, Vec3,
-    WALKABLE_AREA_ID,
+    test_util::{
+      flat_compact_heightfield, floor_compact_heightfield, rasterize_floor,
+    },
+    AreaFilterMode, AreaFilterOptions, AreaVolume, AreaVolumeShape,
+    CompactHeightfield, Context, ContourBuildFlags, ContourSet, Floor,
+    HasRegions, Heightfield, NoRegions, PartitionStrategy, PolyMesh,
+    SampleMode, Vec3, INVALID_AREA_ID, WALKABLE_AREA_ID,
   };
 
   macro_rules! assert_span_column_eq {
@@ -699,6 +1504,67 @@ mod tests {
     );
   }
 
+  #[test]
+  fn erode_area_per_area() {
+    let mut context = Context::new();
+
+    let create_compact_heightfield = |context: &mut Context| {
+      let mut compact_heightfield =
+        floor_compact_heightfield(context, Vec3::new(8.0, 5.0, 8.0));
+      // An obstacle in the middle of the heightfield.
+      compact_heightfield.mark_box_area_with_id(
+        context,
+        Vec3::new(3.0, 0.0, 3.0),
+        Vec3::new(4.99, 1.0, 4.99),
+        INVALID_AREA_ID,
+      );
+      compact_heightfield.mark_box_area_with_id(
+        context,
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(3.99, 1.0, 8.0),
+        1,
+      );
+      compact_heightfield
+    };
+
+    // A constant radius matches `erode_walkable_area`.
+    let mut expected_compact_heightfield =
+      create_compact_heightfield(&mut context);
+    expected_compact_heightfield
+      .erode_walkable_area(&mut context, 2)
+      .expect("erosion succeeds");
+    let mut compact_heightfield = create_compact_heightfield(&mut context);
+    compact_heightfield.erode_walkable_area_per_area(|_| 2);
+    assert_eq!(
+      compact_heightfield.span_areas(),
+      expected_compact_heightfield.span_areas()
+    );
+
+    let mut compact_heightfield = create_compact_heightfield(&mut context);
+    compact_heightfield.erode_walkable_area_per_area(|area_id| {
+      if area_id == 1 {
+        1
+      } else {
+        0
+      }
+    });
+
+    const W: u8 = WALKABLE_AREA_ID;
+    assert_eq!(
+      compact_heightfield.span_areas(),
+      [
+        0, 0, 0, 0, W, W, W, W, //
+        0, 1, 1, 1, W, W, W, W, //
+        0, 1, 1, 0, W, W, W, W, //
+        0, 1, 0, 0, 0, W, W, W, //
+        0, 1, 0, 0, 0, W, W, W, //
+        0, 1, 1, 0, W, W, W, W, //
+        0, 1, 1, 1, W, W, W, W, //
+        0, 0, 0, 0, W, W, W, W, //
+      ]
+    );
+  }
+
   #[test]
   fn marks_areas() {
     let mut context = Context::new();
@@ -784,6 +1650,262 @@ mod tests {
     );
   }
 
+  #[test]
+  fn marks_offset_convex_poly_area() {
+    let mut context = Context::new();
+
+    let mut compact_heightfield =
+      floor_compact_heightfield(&mut context, Vec3::new(8.0, 10.0, 8.0));
+
+    let square = [
+      Vec3::new(3.0, 0.0, 3.0),
+      Vec3::new(5.0, 0.0, 3.0),
+      Vec3::new(5.0, 0.0, 5.0),
+      Vec3::new(3.0, 0.0, 5.0),
+    ];
+
+    compact_heightfield.mark_offset_convex_poly_area_with_id(
+      &mut context,
+      &square,
+      1.0,
+      0.0,
+      2.0,
+      2,
+    );
+    compact_heightfield.mark_convex_poly_area_with_id(
+      &mut context,
+      &square,
+      0.0,
+      2.0,
+      1,
+    );
+
+    const W: u8 = WALKABLE_AREA_ID;
+    // The offset polygon covers an extra cell on each side (the bevelled
+    // corners still contain the corner cell centres).
+    assert_eq!(
+      compact_heightfield.span_areas(),
+      [
+        W, W, W, W, W, W, W, W, //
+        W, W, W, W, W, W, W, W, //
+        W, W, 2, 2, 2, 2, W, W, //
+        W, W, 2, 1, 1, 2, W, W, //
+        W, W, 2, 1, 1, 2, W, W, //
+        W, W, 2, 2, 2, 2, W, W, //
+        W, W, W, W, W, W, W, W, //
+        W, W, W, W, W, W, W, W, //
+      ]
+    );
+  }
+
+  #[test]
+  fn marks_area_volumes_by_priority() {
+    let mut context = Context::new();
+
+    let mut volumes = vec![
+      AreaVolume {
+        shape: AreaVolumeShape::Box {
+          min_bounds: Vec3::new(0.0, 0.0, 0.0),
+          max_bounds: Vec3::new(2.99, 1.0, 2.99),
+        },
+        area_id: 1,
+        priority: 1,
+      },
+      AreaVolume {
+        shape: AreaVolumeShape::Cylinder {
+          position: Vec3::new(2.5, 1.0, 7.5),
+          radius: 2.5,
+          height: 2.0,
+        },
+        area_id: 2,
+        priority: 1,
+      },
+      AreaVolume {
+        shape: AreaVolumeShape::ConvexPoly {
+          vertices: vec![
+            Vec3::new(5.0, 0.0, 0.0),
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(10.0, 0.0, 3.0),
+            Vec3::new(8.0, 0.0, 8.0),
+            Vec3::new(7.0, 0.0, 8.0),
+            Vec3::new(5.0, 0.0, 3.0),
+          ],
+          offset: None,
+          base_height: 0.0,
+          top_height: 2.0,
+        },
+        area_id: 4,
+        priority: 1,
+      },
+      // Covers everything, but has a lower priority than the other volumes.
+      AreaVolume {
+        shape: AreaVolumeShape::Box {
+          min_bounds: Vec3::new(0.0, 0.0, 0.0),
+          max_bounds: Vec3::new(10.0, 1.0, 10.0),
+        },
+        area_id: 3,
+        priority: 0,
+      },
+    ];
+
+    const EXPECTED_AREAS: [u8; 100] = [
+      1, 1, 1, 3, 3, 4, 4, 4, 4, 4, //
+      1, 1, 1, 3, 3, 4, 4, 4, 4, 4, //
+      1, 1, 1, 3, 3, 4, 4, 4, 4, 4, //
+      3, 3, 3, 3, 3, 4, 4, 4, 4, 4, //
+      3, 3, 3, 3, 3, 3, 4, 4, 4, 3, //
+      3, 2, 2, 2, 3, 3, 4, 4, 4, 3, //
+      2, 2, 2, 2, 2, 3, 4, 4, 4, 3, //
+      2, 2, 2, 2, 2, 3, 3, 4, 3, 3, //
+      2, 2, 2, 2, 2, 3, 3, 3, 3, 3, //
+      3, 2, 2, 2, 3, 3, 3, 3, 3, 3, //
+    ];
+
+    let mut compact_heightfield = flat_compact_heightfield(&mut context, 10.0);
+    compact_heightfield.mark_area_volumes(&volumes, false);
+    assert_eq!(compact_heightfield.span_areas(), EXPECTED_AREAS);
+
+    // The order of the volumes does not matter.
+    volumes.reverse();
+    let mut compact_heightfield = flat_compact_heightfield(&mut context, 10.0);
+    compact_heightfield.mark_area_volumes(&volumes, false);
+    assert_eq!(compact_heightfield.span_areas(), EXPECTED_AREAS);
+  }
+
+  #[test]
+  fn area_volumes_preserve_invalid_areas() {
+    let mut context = Context::new();
+
+    let volumes = [
+      AreaVolume {
+        shape: AreaVolumeShape::Box {
+          min_bounds: Vec3::new(0.0, 0.0, 0.0),
+          max_bounds: Vec3::new(4.99, 1.0, 10.0),
+        },
+        area_id: INVALID_AREA_ID,
+        priority: 0,
+      },
+      AreaVolume {
+        shape: AreaVolumeShape::Box {
+          min_bounds: Vec3::new(3.0, 0.0, 0.0),
+          max_bounds: Vec3::new(10.0, 1.0, 10.0),
+        },
+        area_id: 2,
+        priority: 1,
+      },
+    ];
+
+    let mut compact_heightfield = flat_compact_heightfield(&mut context, 10.0);
+    compact_heightfield.mark_area_volumes(&volumes, false);
+    for row in compact_heightfield.span_areas().chunks(10) {
+      assert_eq!(row, [0, 0, 0, 2, 2, 2, 2, 2, 2, 2]);
+    }
+
+    let mut compact_heightfield = flat_compact_heightfield(&mut context, 10.0);
+    compact_heightfield.mark_area_volumes(&volumes, true);
+    for row in compact_heightfield.span_areas().chunks(10) {
+      assert_eq!(row, [0, 0, 0, 0, 0, 2, 2, 2, 2, 2]);
+    }
+
+    // Spans that are already invalid are never marked.
+    compact_heightfield.mark_area_volumes(&volumes[1..], false);
+    for row in compact_heightfield.span_areas().chunks(10) {
+      assert_eq!(row, [0, 0, 0, 0, 0, 2, 2, 2, 2, 2]);
+    }
+  }
+
+  #[test]
+  fn finds_floors() {
+    let mut context = Context::new();
+
+    let min_bounds = Vec3::new(0.0, 0.0, 0.0);
+    let max_bounds = Vec3::new(4.0, 10.0, 2.0);
+
+    let mut heightfield =
+      Heightfield::new(&mut context, min_bounds, max_bounds, 1.0, 1.0)
+        .expect("creation succeeds");
+
+    // Lower floor.
+    rasterize_floor(
+      &mut context,
+      &mut heightfield,
+      Vec3::new(0.0, 0.5, 0.0),
+      Vec3::new(2.0, 0.5, 2.0),
+    );
+    // Higher floor.
+    rasterize_floor(
+      &mut context,
+      &mut heightfield,
+      Vec3::new(2.0, 1.5, 0.0),
+      Vec3::new(4.0, 1.5, 2.0),
+    );
+    // Roof over the lower floor.
+    rasterize_floor(
+      &mut context,
+      &mut heightfield,
+      Vec3::new(0.0, 5.5, 0.0),
+      Vec3::new(2.0, 5.5, 2.0),
+    );
+
+    let mut compact_heightfield =
+      CompactHeightfield::<NoRegions>::new(&heightfield, &mut context, 2, 1)
+        .expect("creating CompactHeightfield succeeds");
+
+    assert_eq!(
+      compact_heightfield.floors_at(1.0, 1.0, SampleMode::Nearest),
+      [
+        Floor {
+          span_index: 8,
+          height: 1.0,
+          clearance: 4.0,
+          area_id: WALKABLE_AREA_ID,
+        },
+        Floor {
+          span_index: 9,
+          height: 6.0,
+          clearance: 255.0,
+          area_id: WALKABLE_AREA_ID,
+        },
+      ]
+    );
+
+    // Halfway between the cell centres of the two floors.
+    assert_eq!(
+      compact_heightfield.floors_at(
+        2.0,
+        1.0,
+        SampleMode::Bilinear { max_step: 1.0 }
+      ),
+      [Floor {
+        span_index: 10,
+        height: 1.5,
+        clearance: 255.0,
+        area_id: WALKABLE_AREA_ID,
+      }]
+    );
+
+    assert_eq!(
+      compact_heightfield.floors_at(5.0, 1.0, SampleMode::Nearest),
+      []
+    );
+
+    // Invalid spans are not floors.
+    compact_heightfield.mark_box_area_with_id(
+      &mut context,
+      Vec3::new(0.0, 5.0, 0.0),
+      Vec3::new(2.0, 7.0, 2.0),
+      INVALID_AREA_ID,
+    );
+    assert_eq!(
+      compact_heightfield
+        .floors_at(1.0, 1.0, SampleMode::Nearest)
+        .iter()
+        .map(|floor| floor.span_index)
+        .collect::<Vec<_>>(),
+      [8]
+    );
+  }
+
   #[test]
   fn median_filter_applied() {
     let mut context = Context::new();
@@ -880,6 +2002,168 @@ mod tests {
     );
   }
 
+  #[test]
+  fn filter_area_ids_matches_median_filter() {
+    let mut context = Context::new();
+
+    let create_compact_heightfield = |context: &mut Context| {
+      let mut compact_heightfield = flat_compact_heightfield(context, 10.0);
+      compact_heightfield.mark_box_area_with_id(
+        context,
+        Vec3::new(3.01, 0.0, 3.01),
+        Vec3::new(6.99, 1.0, 6.99),
+        1,
+      );
+      compact_heightfield.mark_box_area_with_id(
+        context,
+        Vec3::new(5.01, 0.0, 5.01),
+        Vec3::new(10.0, 1.0, 10.0),
+        2,
+      );
+      compact_heightfield.mark_box_area_with_id(
+        context,
+        Vec3::new(1.01, 0.0, 1.01),
+        Vec3::new(1.99, 1.0, 1.99),
+        4,
+      );
+      compact_heightfield.mark_box_area_with_id(
+        context,
+        Vec3::new(7.01, 0.0, 1.01),
+        Vec3::new(8.99, 1.0, 2.99),
+        INVALID_AREA_ID,
+      );
+      compact_heightfield
+    };
+
+    let mut expected_compact_heightfield =
+      create_compact_heightfield(&mut context);
+    expected_compact_heightfield
+      .median_filter_area_ids(&mut context)
+      .expect("median filter succeeded");
+
+    let mut compact_heightfield = create_compact_heightfield(&mut context);
+    compact_heightfield.filter_area_ids(&AreaFilterOptions {
+      kernel_radius: 1,
+      mode: AreaFilterMode::Median,
+      preserved_area_ids: vec![],
+    });
+    assert_eq!(
+      compact_heightfield.span_areas(),
+      expected_compact_heightfield.span_areas()
+    );
+  }
+
+  #[test]
+  fn filter_area_ids_with_zero_radius() {
+    let mut context = Context::new();
+
+    let mut compact_heightfield = flat_compact_heightfield(&mut context, 5.0);
+    compact_heightfield.mark_box_area_with_id(
+      &mut context,
+      Vec3::new(2.01, 0.0, 2.01),
+      Vec3::new(2.99, 1.0, 2.99),
+      2,
+    );
+    let expected_areas = compact_heightfield.span_areas().to_vec();
+
+    // A 1x1 kernel only contains the span itself, so nothing changes.
+    for mode in [AreaFilterMode::Median, AreaFilterMode::Majority] {
+      compact_heightfield.filter_area_ids(&AreaFilterOptions {
+        kernel_radius: 0,
+        mode,
+        preserved_area_ids: vec![],
+      });
+      assert_eq!(compact_heightfield.span_areas(), expected_areas);
+    }
+  }
+
+  #[test]
+  fn filter_area_ids_preserves_areas() {
+    let mut context = Context::new();
+
+    let create_compact_heightfield = |context: &mut Context| {
+      let mut compact_heightfield = flat_compact_heightfield(context, 10.0);
+      // A thin bridge across the heightfield, next to a small patch of noise.
+      compact_heightfield.mark_box_area_with_id(
+        context,
+        Vec3::new(4.01, 0.0, 1.01),
+        Vec3::new(4.99, 1.0, 8.99),
+        2,
+      );
+      compact_heightfield.mark_box_area_with_id(
+        context,
+        Vec3::new(6.01, 0.0, 6.01),
+        Vec3::new(6.99, 1.0, 6.99),
+        3,
+      );
+      compact_heightfield
+    };
+
+    // Without preservation, the bridge is filtered out like noise.
+    let mut compact_heightfield = create_compact_heightfield(&mut context);
+    compact_heightfield.filter_area_ids(&AreaFilterOptions {
+      kernel_radius: 1,
+      mode: AreaFilterMode::Median,
+      preserved_area_ids: vec![],
+    });
+    assert!(compact_heightfield
+      .span_areas()
+      .iter()
+      .all(|&area_id| area_id == WALKABLE_AREA_ID));
+
+    // With preservation, the bridge is kept (and does not spread), while the
+    // noise is still removed.
+    let mut compact_heightfield = create_compact_heightfield(&mut context);
+    compact_heightfield.filter_area_ids(&AreaFilterOptions {
+      kernel_radius: 1,
+      mode: AreaFilterMode::Median,
+      preserved_area_ids: vec![2],
+    });
+    for (span_index, &area_id) in
+      compact_heightfield.span_areas().iter().enumerate()
+    {
+      let is_bridge =
+        span_index % 10 == 4 && (1..=8).contains(&(span_index / 10));
+      let expected_area_id = if is_bridge { 2 } else { WALKABLE_AREA_ID };
+      assert_eq!(area_id, expected_area_id, "span {span_index}");
+    }
+  }
+
+  #[test]
+  fn filter_area_ids_by_majority() {
+    let mut context = Context::new();
+
+    let mut compact_heightfield = flat_compact_heightfield(&mut context, 10.0);
+    compact_heightfield.mark_box_area_with_id(
+      &mut context,
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(4.99, 1.0, 10.0),
+      1,
+    );
+    // A 2x2 patch is too small to survive a 5x5 majority vote.
+    compact_heightfield.mark_box_area_with_id(
+      &mut context,
+      Vec3::new(6.01, 0.0, 4.01),
+      Vec3::new(7.99, 1.0, 5.99),
+      2,
+    );
+
+    compact_heightfield.filter_area_ids(&AreaFilterOptions {
+      kernel_radius: 2,
+      mode: AreaFilterMode::Majority,
+      preserved_area_ids: vec![],
+    });
+
+    // The straight boundary between the two halves is unchanged.
+    for (span_index, &area_id) in
+      compact_heightfield.span_areas().iter().enumerate()
+    {
+      let expected_area_id =
+        if span_index % 10 < 5 { 1 } else { WALKABLE_AREA_ID };
+      assert_eq!(area_id, expected_area_id, "span {span_index}");
+    }
+  }
+
   fn build_regions_base(
     build_fn: fn(
       compact_heightfield: CompactHeightfield<NoRegions>,
@@ -929,6 +2213,28 @@ mod tests {
     assert_eq!(compact_heightfield_with_regions.border_size(), 0);
     assert_eq!(compact_heightfield_with_regions.max_region_id(), 2);
     assert_eq!(compact_heightfield_with_regions.max_distance(), 2);
+    assert_eq!(
+      compact_heightfield_with_regions.span_distances(),
+      [
+        0, 0, 0, 0, 0, //
+        0, 0, 0, 0, 0, //
+        0, 0, 2, 0, 0, //
+        0, 0, 0, 0, 0, //
+        0, 0, 0, 0, 0, //
+      ]
+    );
+    assert_eq!(
+      compact_heightfield_with_regions.span_distance_cells(12),
+      Some(1.0)
+    );
+    assert_eq!(
+      compact_heightfield_with_regions.span_distance_world(12),
+      Some(1.0)
+    );
+    assert_eq!(
+      compact_heightfield_with_regions.span_distance_cells(6),
+      Some(0.0)
+    );
 
     assert_eq!(
       compact_heightfield_with_regions
@@ -945,6 +2251,173 @@ mod tests {
     );
   }
 
+  #[test]
+  fn build_regions_from_user_ids() {
+    let mut context = Context::new();
+
+    let create_compact_heightfield = |context: &mut Context| {
+      floor_compact_heightfield(context, Vec3::new(6.0, 5.0, 3.0))
+    };
+
+    // Wrong number of region IDs.
+    assert!(create_compact_heightfield(&mut context)
+      .build_regions_from_ids(&[1; 17])
+      .is_err());
+    // Border region flag.
+    assert!(create_compact_heightfield(&mut context)
+      .build_regions_from_ids(&[0x8001; 18])
+      .is_err());
+
+    let compact_heightfield_with_regions =
+      create_compact_heightfield(&mut context)
+        .build_regions_with(|_, position| if position.x < 2.0 { 1 } else { 3 })
+        .expect("building regions succeeds");
+
+    assert_eq!(compact_heightfield_with_regions.max_region_id(), 4);
+    assert_eq!(compact_heightfield_with_regions.border_size(), 0);
+    // No distance field was built.
+    assert_eq!(compact_heightfield_with_regions.span_distance_cells(0), None);
+    assert_eq!(compact_heightfield_with_regions.span_distance_world(0), None);
+    assert_eq!(
+      compact_heightfield_with_regions
+        .spans_iter()
+        .map(|span| span.region_id())
+        .collect::<Vec<_>>(),
+      [
+        1, 1, 3, 3, 3, 3, //
+        1, 1, 3, 3, 3, 3, //
+        1, 1, 3, 3, 3, 3, //
+      ]
+    );
+
+    // The custom regions can be used by the rest of the pipeline.
+    let contour_set = ContourSet::new(
+      &compact_heightfield_with_regions,
+      &mut context,
+      /* max_error= */ 1.0,
+      /* max_edge_len= */ 10,
+      ContourBuildFlags {
+        tessellate_wall_edges: true,
+        tessellate_area_edges: false,
+      },
+    )
+    .expect("contours built");
+
+    let poly_mesh = PolyMesh::new(
+      &contour_set,
+      &mut context,
+      /* max_vertices_per_polygon= */ 6,
+    )
+    .expect("poly mesh built");
+
+    let mut polygon_region_ids = poly_mesh
+      .polygons_iter()
+      .map(|polygon| polygon.region_id())
+      .collect::<Vec<_>>();
+    polygon_region_ids.sort();
+    assert_eq!(polygon_region_ids, [1, 3]);
+  }
+
+  #[test]
+  fn partition_with_strategy() {
+    build_regions_base(|compact_heightfield, context| {
+      compact_heightfield.partition(
+        context,
+        PartitionStrategy::Watershed {
+          border_size: 0,
+          min_region_area: 1,
+          merge_region_area: 1,
+        },
+      )
+    });
+    build_regions_base(|compact_heightfield, context| {
+      compact_heightfield.partition(
+        context,
+        PartitionStrategy::Monotone {
+          border_size: 0,
+          min_region_area: 1,
+          merge_region_area: 1,
+        },
+      )
+    });
+    build_regions_base(|compact_heightfield, context| {
+      compact_heightfield.partition(
+        context,
+        PartitionStrategy::Layers { border_size: 0, min_region_area: 1 },
+      )
+    });
+  }
+
+  #[test]
+  fn clears_regions() {
+    let mut context = Context::new();
+
+    let compact_heightfield_with_regions =
+      floor_compact_heightfield(&mut context, Vec3::new(6.0, 5.0, 3.0))
+        .build_regions_with(|_, position| if position.x < 3.0 { 1 } else { 2 })
+        .expect("building regions succeeds");
+    assert_eq!(compact_heightfield_with_regions.max_region_id(), 3);
+
+    let mut compact_heightfield =
+      compact_heightfield_with_regions.clear_regions();
+
+    // Split the area so that the spans in the middle form a new region.
+    compact_heightfield.mark_box_area_with_id(
+      &mut context,
+      Vec3::new(2.0, 0.0, 0.0),
+      Vec3::new(3.99, 1.0, 3.0),
+      1,
+    );
+
+    let compact_heightfield_with_regions = compact_heightfield
+      .build_regions_monotone(
+        &mut context,
+        /* border_size= */ 0,
+        /* min_region_area= */ 1,
+        /* merge_region_area= */ 1,
+      )
+      .expect("building regions succeeds");
+
+    assert_eq!(compact_heightfield_with_regions.max_region_id(), 4);
+    assert_eq!(
+      compact_heightfield_with_regions
+        .spans_iter()
+        .map(|span| span.region_id())
+        .collect::<Vec<_>>(),
+      [
+        1, 1, 2, 2, 3, 3, //
+        1, 1, 2, 2, 3, 3, //
+        1, 1, 2, 2, 3, 3, //
+      ]
+    );
+  }
+
+  #[test]
+  fn clearing_regions_discards_distance_field() {
+    let mut context = Context::new();
+
+    let compact_heightfield = flat_compact_heightfield(&mut context, 6.0)
+      .build_regions(
+        &mut context,
+        /* border_size= */ 0,
+        /* min_region_area= */ 1,
+        /* merge_region_area= */ 1,
+      )
+      .expect("building regions succeeds");
+    assert!(compact_heightfield.span_distance_cells(0).is_some());
+
+    let compact_heightfield = compact_heightfield.clear_regions();
+    let region_ids = vec![1; compact_heightfield.spans_len()];
+    let compact_heightfield = compact_heightfield
+      .build_regions_from_ids(&region_ids)
+      .expect("building regions succeeds");
+
+    assert_eq!(compact_heightfield.max_distance(), 0);
+    assert!(compact_heightfield.span_distances().is_empty());
+    assert_eq!(compact_heightfield.span_distance_cells(0), None);
+    assert_eq!(compact_heightfield.span_distance_world(0), None);
+  }
+
   #[test]
   fn build_regions() {
     fn build_fn(
@@ -1,8 +1,8 @@
 use std::ops::{Deref, DerefMut};
 
-use recastnavigation_sys::rcBuildContours;
+use recastnavigation_sys::{rcBuildContours, rcContour};
 
-use crate::{wrappers, CompactHeightfield, Context, HasRegions};
+use crate::{wrappers, CompactHeightfield, Context, HasRegions, Vec3};
 
 pub struct ContourBuildFlags {
   // Tessellate solid (impassable) edges during simplification.
@@ -55,6 +55,140 @@ impl ContourSet {
       Err(())
     }
   }
+
+  pub fn contours_len(&self) -> usize {
+    self.contour_set.nconts as usize
+  }
+
+  // Gets a Contour by index.
+  pub fn contour(&self, index: usize) -> Contour<'_> {
+    assert!(
+      index < self.contours_len(),
+      "Contour index out-of-bounds. index={}, len={}",
+      index,
+      self.contours_len()
+    );
+    Contour { contour_set: self, index }
+  }
+
+  // Creates an Iterator of all contours.
+  pub fn contours_iter(&self) -> impl Iterator<Item = Contour<'_>> + '_ {
+    (0..self.contours_len()).map(|index| Contour { contour_set: self, index })
+  }
+
+  pub fn min_bounds(&self) -> Vec3<f32> {
+    Vec3::new(
+      self.contour_set.bmin[0],
+      self.contour_set.bmin[1],
+      self.contour_set.bmin[2],
+    )
+  }
+
+  pub fn max_bounds(&self) -> Vec3<f32> {
+    Vec3::new(
+      self.contour_set.bmax[0],
+      self.contour_set.bmax[1],
+      self.contour_set.bmax[2],
+    )
+  }
+
+  pub fn cell_horizontal_size(&self) -> f32 {
+    self.contour_set.cs
+  }
+
+  pub fn cell_height(&self) -> f32 {
+    self.contour_set.ch
+  }
+
+  pub fn border_size(&self) -> i32 {
+    self.contour_set.borderSize
+  }
+
+  pub fn max_error(&self) -> f32 {
+    self.contour_set.maxError
+  }
+}
+
+// A vertex of a Contour. `position` is in grid coordinates relative to the
+// ContourSet's `min_bounds` (use `as_f32` for "world space").
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ContourVertex {
+  pub position: Vec3<i32>,
+  // The region ID of the region on the other side of the edge starting at this
+  // vertex (in the lower 16 bits), combined with Recast's border vertex and
+  // area border flags.
+  pub flags: i32,
+}
+
+impl ContourVertex {
+  // Gets the vertex in "world space". `contour_set` must be the ContourSet
+  // containing the vertex.
+  pub fn as_f32(&self, contour_set: &ContourSet) -> Vec3<f32> {
+    let min_bounds = contour_set.min_bounds();
+    Vec3::new(
+      min_bounds.x
+        + self.position.x as f32 * contour_set.cell_horizontal_size(),
+      min_bounds.y + self.position.y as f32 * contour_set.cell_height(),
+      min_bounds.z
+        + self.position.z as f32 * contour_set.cell_horizontal_size(),
+    )
+  }
+}
+
+// A single contour (the outline of a region) in a ContourSet.
+pub struct Contour<'contour_set> {
+  contour_set: &'contour_set ContourSet,
+  index: usize,
+}
+
+impl<'contour_set> Contour<'contour_set> {
+  fn raw_contour(&self) -> &'contour_set rcContour {
+    // SAFETY: `conts` has `nconts` contours, and `index` is less than `nconts`
+    // (checked when creating the Contour). The lifetime is correct since the
+    // ContourSet owns the contours.
+    unsafe { &*self.contour_set.contour_set.conts.add(self.index) }
+  }
+
+  pub fn region_id(&self) -> u16 {
+    self.raw_contour().reg
+  }
+
+  pub fn area_id(&self) -> u8 {
+    self.raw_contour().area
+  }
+
+  // Gets the vertices of the simplified contour, which are used to build the
+  // PolyMesh.
+  pub fn vertices(&self) -> &'contour_set [ContourVertex] {
+    let raw_contour = self.raw_contour();
+    self.vertices_slice(raw_contour.verts, raw_contour.nverts)
+  }
+
+  // Gets the vertices of the raw contour, which follows the edges of the
+  // region's cells exactly.
+  pub fn raw_vertices(&self) -> &'contour_set [ContourVertex] {
+    let raw_contour = self.raw_contour();
+    self.vertices_slice(raw_contour.rverts, raw_contour.nrverts)
+  }
+
+  fn vertices_slice(
+    &self,
+    vertices: *const i32,
+    len: i32,
+  ) -> &'contour_set [ContourVertex] {
+    if vertices.is_null() || len <= 0 {
+      return &[];
+    }
+
+    // SAFETY: Each contour vertex is 4 i32's (x, y, z and flags), which lines
+    // up perfectly with ContourVertex (which is repr(C)). `vertices` is not
+    // null, so it has `len` vertices. The lifetime is correct since the
+    // ContourSet owns the vertices.
+    unsafe {
+      std::slice::from_raw_parts(vertices as *const ContourVertex, len as usize)
+    }
+  }
 }
 
 #[cfg(test)]
@@ -98,7 +232,7 @@ mod tests {
       .build_regions(&mut context, 0, 1, 1)
       .expect("regions built");
 
-    ContourSet::new(
+    let contour_set = ContourSet::new(
       &compact_heightfield_with_regions,
       &mut context,
       /* max_error= */ 1.0,
@@ -109,5 +243,33 @@ mod tests {
       },
     )
     .expect("contours built");
+
+    assert_eq!(contour_set.contours_len(), 1);
+    assert_eq!(contour_set.min_bounds(), min_bounds);
+    assert_eq!(contour_set.cell_horizontal_size(), 1.0);
+
+    let contour = contour_set.contour(0);
+    assert_eq!(contour.region_id(), 1);
+    assert_eq!(contour.area_id(), WALKABLE_AREA_ID);
+    // The simplified contour is the corners of the square.
+    assert_eq!(
+      contour
+        .vertices()
+        .iter()
+        .map(|vertex| vertex.as_f32(&contour_set))
+        .collect::<Vec<_>>(),
+      [
+        Vec3::new(0.0, 1.0, 0.0),
+        Vec3::new(0.0, 1.0, 5.0),
+        Vec3::new(5.0, 1.0, 5.0),
+        Vec3::new(5.0, 1.0, 0.0),
+      ]
+    );
+    // The raw contour has a vertex at each cell along the edges.
+    assert_eq!(contour.raw_vertices().len(), 4 * 5);
+    assert!(contour.raw_vertices().iter().all(|vertex| {
+      vertex.position.y == 1
+        && (vertex.position.x % 5 == 0 || vertex.position.z % 5 == 0)
+    }));
   }
 }
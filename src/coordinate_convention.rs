@@ -0,0 +1,414 @@
+use std::ops::Neg;
+
+use crate::{ContourSet, ContourVertex, PolyMesh, PolyMeshDetail, Vec3};
+
+// The axis that points up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpAxis {
+  Y,
+  Z,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Handedness {
+  Right,
+  Left,
+}
+
+// The coordinate convention of input and output data. Recast uses a Y-up,
+// right-handed convention, so data in any other convention must be converted
+// before being passed to Recast, and outputs converted back.
+//
+// The X axis is kept in all conversions. For Z-up conventions, Recast's Y axis
+// is the up axis, and Recast's Z axis is the remaining axis (negated for the
+// right-handed convention so the conversion is a rotation). For the Y-up,
+// left-handed convention, the Z axis is negated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoordinateConvention {
+  pub up_axis: UpAxis,
+  pub handedness: Handedness,
+}
+
+impl Default for CoordinateConvention {
+  fn default() -> Self {
+    Self::RECAST
+  }
+}
+
+impl CoordinateConvention {
+  // The convention used by Recast.
+  pub const RECAST: Self =
+    Self { up_axis: UpAxis::Y, handedness: Handedness::Right };
+
+  // Whether converting to Recast's convention mirrors positions. Mirroring
+  // flips the winding of triangles and polygons.
+  pub fn is_mirroring(&self) -> bool {
+    self.handedness == Handedness::Left
+  }
+
+  // Converts a position in this convention to Recast's convention.
+  pub fn to_recast<T: Copy + Neg<Output = T>>(
+    &self,
+    position: Vec3<T>,
+  ) -> Vec3<T> {
+    let Vec3 { x, y, z } = position;
+    match (self.up_axis, self.handedness) {
+      (UpAxis::Y, Handedness::Right) => position,
+      (UpAxis::Y, Handedness::Left) => Vec3::new(x, y, -z),
+      (UpAxis::Z, Handedness::Right) => Vec3::new(x, z, -y),
+      (UpAxis::Z, Handedness::Left) => Vec3::new(x, z, y),
+    }
+  }
+
+  // Converts a position in Recast's convention to this convention.
+  pub fn from_recast<T: Copy + Neg<Output = T>>(
+    &self,
+    position: Vec3<T>,
+  ) -> Vec3<T> {
+    let Vec3 { x, y, z } = position;
+    match (self.up_axis, self.handedness) {
+      (UpAxis::Y, Handedness::Right) => position,
+      (UpAxis::Y, Handedness::Left) => Vec3::new(x, y, -z),
+      (UpAxis::Z, Handedness::Right) => Vec3::new(x, -z, y),
+      (UpAxis::Z, Handedness::Left) => Vec3::new(x, z, y),
+    }
+  }
+
+  // Converts positions in this convention to Recast's convention.
+  pub fn vertices_to_recast(&self, vertices: &[Vec3<f32>]) -> Vec<Vec3<f32>> {
+    vertices.iter().map(|&vertex| self.to_recast(vertex)).collect()
+  }
+
+  // Converts triangles in this convention to Recast's convention. Triangles
+  // are rewound if the conversion mirrors, so triangles whose normals point up
+  // in this convention also point up in Recast's convention (e.g. for
+  // `util::mark_walkable_triangles`). Normals are computed as `(v1 - v0) x
+  // (v2 - v0)` in both conventions.
+  pub fn triangles_to_recast(&self, triangles: &[Vec3<i32>]) -> Vec<Vec3<i32>> {
+    if self.is_mirroring() {
+      triangles
+        .iter()
+        .map(|triangle| Vec3::new(triangle.x, triangle.z, triangle.y))
+        .collect()
+    } else {
+      triangles.to_vec()
+    }
+  }
+
+  // Converts bounds in this convention to bounds in Recast's convention. The
+  // returned tuple is `(min_bounds, max_bounds)`.
+  pub fn bounds_to_recast(
+    &self,
+    min_bounds: Vec3<f32>,
+    max_bounds: Vec3<f32>,
+  ) -> (Vec3<f32>, Vec3<f32>) {
+    sorted_bounds(self.to_recast(min_bounds), self.to_recast(max_bounds))
+  }
+
+  // Converts bounds in Recast's convention (e.g. `HeightfieldLayer` bounds) to
+  // bounds in this convention. The returned tuple is `(min_bounds,
+  // max_bounds)`.
+  pub fn bounds_from_recast(
+    &self,
+    min_bounds: Vec3<f32>,
+    max_bounds: Vec3<f32>,
+  ) -> (Vec3<f32>, Vec3<f32>) {
+    sorted_bounds(self.from_recast(min_bounds), self.from_recast(max_bounds))
+  }
+
+  // Returns the world positions of the vertices of `poly_mesh` in this
+  // convention.
+  pub fn poly_mesh_vertices(&self, poly_mesh: &PolyMesh) -> Vec<Vec3<f32>> {
+    let min_bounds = poly_mesh.min_bounds();
+    poly_mesh
+      .vertices_iter()
+      .map(|vertex| {
+        let vertex = vertex.as_f32();
+        self.from_recast(Vec3::new(
+          min_bounds.x + vertex.x,
+          min_bounds.y + vertex.y,
+          min_bounds.z + vertex.z,
+        ))
+      })
+      .collect()
+  }
+
+  // Returns the vertices of `poly_mesh_detail` in this convention.
+  pub fn poly_mesh_detail_vertices(
+    &self,
+    poly_mesh_detail: &PolyMeshDetail,
+  ) -> Vec<Vec3<f32>> {
+    poly_mesh_detail
+      .vertices()
+      .iter()
+      .map(|&vertex| self.from_recast(vertex))
+      .collect()
+  }
+
+  // Returns the world positions of contour `vertices` (e.g.
+  // `Contour::vertices` or `Contour::raw_vertices`) of `contour_set` in this
+  // convention.
+  pub fn contour_vertices_from_recast(
+    &self,
+    contour_set: &ContourSet,
+    vertices: &[ContourVertex],
+  ) -> Vec<Vec3<f32>> {
+    vertices
+      .iter()
+      .map(|vertex| self.from_recast(vertex.as_f32(contour_set)))
+      .collect()
+  }
+
+  // Reverses the vertex order of an output polygon or triangle if the
+  // conversion mirrors, so its winding matches the winding of inputs in this
+  // convention. `polygon` must not include any `NULL_INDEX` padding.
+  pub fn fix_winding<T>(&self, polygon: &mut [T]) {
+    if self.is_mirroring() {
+      polygon.reverse();
+    }
+  }
+}
+
+fn sorted_bounds(
+  corner_a: Vec3<f32>,
+  corner_b: Vec3<f32>,
+) -> (Vec3<f32>, Vec3<f32>) {
+  (
+    Vec3::new(
+      corner_a.x.min(corner_b.x),
+      corner_a.y.min(corner_b.y),
+      corner_a.z.min(corner_b.z),
+    ),
+    Vec3::new(
+      corner_a.x.max(corner_b.x),
+      corner_a.y.max(corner_b.y),
+      corner_a.z.max(corner_b.z),
+    ),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    util, CompactHeightfield, Context, ContourBuildFlags, ContourSet,
+    CoordinateConvention, Handedness, Heightfield, NoRegions, PolyMesh,
+    PolyMeshDetail, UpAxis, Vec3, NULL_INDEX, WALKABLE_AREA_ID,
+  };
+
+  const Y_UP_LEFT: CoordinateConvention =
+    CoordinateConvention { up_axis: UpAxis::Y, handedness: Handedness::Left };
+  const Z_UP_RIGHT: CoordinateConvention =
+    CoordinateConvention { up_axis: UpAxis::Z, handedness: Handedness::Right };
+  const Z_UP_LEFT: CoordinateConvention =
+    CoordinateConvention { up_axis: UpAxis::Z, handedness: Handedness::Left };
+
+  #[test]
+  fn converts_positions() {
+    let position = Vec3::new(1.0, 2.0, 3.0);
+    for convention in
+      [CoordinateConvention::RECAST, Y_UP_LEFT, Z_UP_RIGHT, Z_UP_LEFT]
+    {
+      assert_eq!(
+        convention.from_recast(convention.to_recast(position)),
+        position
+      );
+    }
+
+    assert_eq!(Z_UP_RIGHT.to_recast(position), Vec3::new(1.0, 3.0, -2.0));
+    assert_eq!(Z_UP_LEFT.to_recast(position), Vec3::new(1.0, 3.0, 2.0));
+    assert_eq!(Y_UP_LEFT.to_recast(position), Vec3::new(1.0, 2.0, -3.0));
+    assert_eq!(
+      Z_UP_RIGHT.bounds_from_recast(
+        Vec3::new(0.0, 1.0, -5.0),
+        Vec3::new(4.0, 2.0, 0.0)
+      ),
+      (Vec3::new(0.0, 0.0, 1.0), Vec3::new(4.0, 5.0, 2.0))
+    );
+
+    let mut triangle = [0, 1, 2];
+    Z_UP_RIGHT.fix_winding(&mut triangle);
+    assert_eq!(triangle, [0, 1, 2]);
+    Z_UP_LEFT.fix_winding(&mut triangle);
+    assert_eq!(triangle, [2, 1, 0]);
+  }
+
+  #[test]
+  fn classifies_slopes_in_all_conventions() {
+    // A flat triangle and a steep triangle in each convention, both with
+    // normals pointing up.
+    let cases = [
+      (
+        CoordinateConvention::RECAST,
+        [
+          Vec3::new(0.0, 0.0, 0.0),
+          Vec3::new(0.0, 0.0, 1.0),
+          Vec3::new(1.0, 0.0, 0.0),
+          Vec3::new(1.0, 3.0, 1.0),
+        ],
+      ),
+      (
+        Y_UP_LEFT,
+        [
+          Vec3::new(0.0, 0.0, 0.0),
+          Vec3::new(1.0, 0.0, 0.0),
+          Vec3::new(0.0, 0.0, -1.0),
+          Vec3::new(1.0, 3.0, -1.0),
+        ],
+      ),
+      (
+        Z_UP_RIGHT,
+        [
+          Vec3::new(0.0, 0.0, 0.0),
+          Vec3::new(1.0, 0.0, 0.0),
+          Vec3::new(0.0, 1.0, 0.0),
+          Vec3::new(1.0, 1.0, 3.0),
+        ],
+      ),
+      (
+        Z_UP_LEFT,
+        [
+          Vec3::new(0.0, 0.0, 0.0),
+          Vec3::new(1.0, 0.0, 0.0),
+          Vec3::new(0.0, 1.0, 0.0),
+          Vec3::new(1.0, 1.0, 3.0),
+        ],
+      ),
+    ];
+
+    let mut context = Context::new();
+    for (convention, vertices) in cases {
+      let mut area_ids = [0, 0];
+      util::mark_walkable_triangles(
+        &mut context,
+        45.0,
+        &convention.vertices_to_recast(&vertices),
+        &convention
+          .triangles_to_recast(&[Vec3::new(0, 1, 2), Vec3::new(0, 1, 3)]),
+        &mut area_ids,
+      );
+      assert_eq!(
+        area_ids,
+        [WALKABLE_AREA_ID, 0],
+        "Incorrect slopes for {:?}",
+        convention
+      );
+    }
+  }
+
+  #[test]
+  fn builds_z_up_poly_mesh() {
+    let mut context = Context::new();
+
+    let (min_bounds, max_bounds) = Z_UP_RIGHT
+      .bounds_to_recast(Vec3::new(0.0, 0.0, 0.0), Vec3::new(5.0, 5.0, 5.0));
+    let mut heightfield =
+      Heightfield::new(&mut context, min_bounds, max_bounds, 0.5, 0.5)
+        .expect("creation succeeds");
+
+    let vertices = [
+      Vec3::new(0.0, 0.0, 1.0),
+      Vec3::new(5.0, 0.0, 1.0),
+      Vec3::new(5.0, 5.0, 1.0),
+      Vec3::new(0.0, 5.0, 1.0),
+    ];
+    heightfield
+      .rasterize_indexed_triangles_i32(
+        &mut context,
+        &Z_UP_RIGHT.vertices_to_recast(&vertices),
+        &Z_UP_RIGHT
+          .triangles_to_recast(&[Vec3::new(0, 1, 2), Vec3::new(0, 2, 3)]),
+        &[WALKABLE_AREA_ID, WALKABLE_AREA_ID],
+        1,
+      )
+      .expect("rasterization succeeds");
+
+    let compact_heightfield =
+      CompactHeightfield::<NoRegions>::new(&heightfield, &mut context, 3, 0)
+        .expect("creating CompactHeightfield succeeds")
+        .build_regions(&mut context, 0, 1, 1)
+        .expect("regions built");
+    let contour_set = ContourSet::new(
+      &compact_heightfield,
+      &mut context,
+      1.0,
+      10,
+      ContourBuildFlags {
+        tessellate_wall_edges: true,
+        tessellate_area_edges: false,
+      },
+    )
+    .expect("contours built");
+    let poly_mesh =
+      PolyMesh::new(&contour_set, &mut context, 6).expect("poly mesh built");
+    let poly_mesh_detail = PolyMeshDetail::new(
+      &poly_mesh,
+      &mut context,
+      &compact_heightfield,
+      1.0,
+      0.1,
+    )
+    .expect("poly mesh detail built");
+
+    assert_eq!(contour_set.contours_len(), 1);
+    let contour = contour_set.contour(0);
+    let contour_vertices =
+      Z_UP_RIGHT.contour_vertices_from_recast(&contour_set, contour.vertices());
+    let mut contour_corners = contour_vertices
+      .iter()
+      .map(|vertex| {
+        assert!(
+          (1.0..=2.0).contains(&vertex.z),
+          "Vertex is not on the floor. vertex={:?}",
+          vertex
+        );
+        (vertex.x, vertex.y)
+      })
+      .collect::<Vec<_>>();
+    contour_corners
+      .sort_by(|a, b| a.partial_cmp(b).expect("positions are not NaN"));
+    assert_eq!(
+      contour_corners,
+      [(0.0, 0.0), (0.0, 5.0), (5.0, 0.0), (5.0, 5.0)]
+    );
+    assert_eq!(
+      Z_UP_RIGHT
+        .contour_vertices_from_recast(&contour_set, contour.raw_vertices())
+        .len(),
+      4 * 10
+    );
+
+    let poly_mesh_vertices = Z_UP_RIGHT.poly_mesh_vertices(&poly_mesh);
+    let detail_vertices =
+      Z_UP_RIGHT.poly_mesh_detail_vertices(&poly_mesh_detail);
+    assert!(!poly_mesh_vertices.is_empty());
+    assert!(!detail_vertices.is_empty());
+    for vertex in poly_mesh_vertices.iter().chain(detail_vertices.iter()) {
+      assert!(
+        (0.0..=5.0).contains(&vertex.x)
+          && (0.0..=5.0).contains(&vertex.y)
+          && (1.0..=2.0).contains(&vertex.z),
+        "Vertex is not on the floor. vertex={:?}",
+        vertex
+      );
+    }
+
+    // The polygons keep the winding of the input triangles (counter-clockwise
+    // when viewed from above).
+    for polygon in poly_mesh.polygons_iter() {
+      let polygon = polygon
+        .vertices()
+        .iter()
+        .take_while(|&&index| index != NULL_INDEX)
+        .map(|&index| poly_mesh_vertices[index as usize])
+        .collect::<Vec<_>>();
+      let doubled_area = (0..polygon.len())
+        .map(|i| {
+          let a = polygon[i];
+          let b = polygon[(i + 1) % polygon.len()];
+          a.x * b.y - b.x * a.y
+        })
+        .sum::<f32>();
+      assert!(doubled_area > 0.0, "Polygon is clockwise: {:?}", polygon);
+    }
+  }
+}
@@ -0,0 +1,337 @@
+use std::collections::BinaryHeap;
+
+use crate::{
+  span_graph::{self, distance, OpenNode, SpanGraph},
+  CompactHeightfield, CompactHeightfieldState, Vec3, INVALID_AREA_ID,
+};
+
+// A flow field leading from every reachable span of a CompactHeightfield to the
+// cheapest of a set of targets. This is built once for all units moving to the
+// same targets, rather than pathfinding for each unit. Spans are identified by
+// their index, so the flow field must only be used with the CompactHeightfield
+// it was created from.
+#[derive(Debug, Clone)]
+pub struct FlowField {
+  target_spans: Vec<usize>,
+  // The integration field: the cost to reach the nearest target from each
+  // span.
+  costs: Vec<f32>,
+  // The span to move to from each span, or usize::MAX for none.
+  next_spans: Vec<usize>,
+  // The normalized XZ direction to move in from each span (zero for none).
+  directions: Vec<Vec3<f32>>,
+}
+
+impl FlowField {
+  // Creates a flow field leading to the floors closest to `targets`.
+  // `area_cost` returns the cost of travelling one world unit in an area, or
+  // None if the area is impassable (`INVALID_AREA_ID` is always impassable).
+  // Targets without a passable floor are ignored.
+  pub fn new(
+    compact_heightfield: &CompactHeightfield<impl CompactHeightfieldState>,
+    targets: &[Vec3<f32>],
+    area_cost: impl Fn(u8) -> Option<f32>,
+  ) -> FlowField {
+    let passable = |span_index: usize| {
+      span_cost(compact_heightfield, &area_cost, span_index).is_some()
+    };
+    let mut target_spans = targets
+      .iter()
+      .filter_map(|&target| {
+        span_graph::closest_floor(compact_heightfield, target, passable)
+      })
+      .collect::<Vec<_>>();
+    target_spans.sort();
+    target_spans.dedup();
+
+    let spans_len = compact_heightfield.spans_len();
+    let mut flow_field = FlowField {
+      target_spans,
+      costs: vec![f32::INFINITY; spans_len],
+      next_spans: vec![usize::MAX; spans_len],
+      directions: vec![Vec3::new(0.0, 0.0, 0.0); spans_len],
+    };
+
+    let graph = SpanGraph::new(compact_heightfield);
+    let mut open = BinaryHeap::new();
+    for &target_span in flow_field.target_spans.iter() {
+      flow_field.costs[target_span] = 0.0;
+      open.push(OpenNode { cost: 0.0, span_index: target_span });
+    }
+    flow_field.propagate(&graph, &area_cost, open);
+    flow_field
+  }
+
+  // Recomputes the flow field after the areas of spans within the XZ bounds of
+  // `min_bounds` and `max_bounds` have changed. Only spans whose cost could
+  // have changed are recomputed. `area_cost` should be the same as when the
+  // flow field was created.
+  pub fn update_region(
+    &mut self,
+    compact_heightfield: &CompactHeightfield<impl CompactHeightfieldState>,
+    min_bounds: Vec3<f32>,
+    max_bounds: Vec3<f32>,
+    area_cost: impl Fn(u8) -> Option<f32>,
+  ) {
+    let graph = SpanGraph::new(compact_heightfield);
+    let min_bounds_grid = compact_heightfield.min_bounds();
+    let cell_horizontal_size = compact_heightfield.cell_horizontal_size();
+    let to_grid = |value: f32, min_value: f32| {
+      ((value - min_value) / cell_horizontal_size).floor() as i32
+    };
+    let (min_x, min_z) = (
+      to_grid(min_bounds.x, min_bounds_grid.x),
+      to_grid(min_bounds.z, min_bounds_grid.z),
+    );
+    let (max_x, max_z) = (
+      to_grid(max_bounds.x, min_bounds_grid.x),
+      to_grid(max_bounds.z, min_bounds_grid.z),
+    );
+    // Diagonal moves depend on the spans next to them, so spans bordering the
+    // region may have also changed.
+    let in_region = |span_index: usize| {
+      let (x, z) = graph.span_cell(span_index);
+      min_x - 1 <= x && x <= max_x + 1 && min_z - 1 <= z && z <= max_z + 1
+    };
+
+    // Invalidate the changed spans and every span whose route passes through
+    // them. Routes always lead to cheaper spans, so visiting spans in order of
+    // cost visits each span after the span it leads to.
+    let mut spans_by_cost = (0..self.costs.len())
+      .filter(|&span_index| self.costs[span_index].is_finite())
+      .collect::<Vec<_>>();
+    spans_by_cost.sort_by(|&a, &b| self.costs[a].total_cmp(&self.costs[b]));
+
+    let mut invalid = (0..self.costs.len()).map(in_region).collect::<Vec<_>>();
+    for span_index in spans_by_cost {
+      let next_span = self.next_spans[span_index];
+      if next_span != usize::MAX && invalid[next_span] {
+        invalid[span_index] = true;
+      }
+    }
+
+    for (span_index, &invalid) in invalid.iter().enumerate() {
+      if invalid {
+        self.costs[span_index] = f32::INFINITY;
+        self.next_spans[span_index] = usize::MAX;
+        self.directions[span_index] = Vec3::new(0.0, 0.0, 0.0);
+      }
+    }
+
+    // Restart the search from the targets that were invalidated and from the
+    // valid spans surrounding the invalidated spans.
+    let mut open = BinaryHeap::new();
+    for &target_span in self.target_spans.iter() {
+      if invalid[target_span]
+        && span_cost(compact_heightfield, &area_cost, target_span).is_some()
+      {
+        self.costs[target_span] = 0.0;
+        open.push(OpenNode { cost: 0.0, span_index: target_span });
+      }
+    }
+    for span_index in 0..self.costs.len() {
+      if invalid[span_index] || self.costs[span_index].is_infinite() {
+        continue;
+      }
+      let borders_invalid = graph
+        .neighbours(span_index, |_| true)
+        .into_iter()
+        .any(|neighbour_index| invalid[neighbour_index]);
+      if borders_invalid {
+        open.push(OpenNode { cost: self.costs[span_index], span_index });
+      }
+    }
+
+    self.propagate(&graph, &area_cost, open);
+  }
+
+  // Runs Dijkstra's algorithm outwards from the spans in `open`.
+  fn propagate<TypeState: CompactHeightfieldState>(
+    &mut self,
+    graph: &SpanGraph<TypeState>,
+    area_cost: &impl Fn(u8) -> Option<f32>,
+    mut open: BinaryHeap<OpenNode>,
+  ) {
+    let compact_heightfield = graph.compact_heightfield;
+    let passable = |span_index: usize| {
+      span_cost(compact_heightfield, area_cost, span_index).is_some()
+    };
+
+    while let Some(OpenNode { cost, span_index }) = open.pop() {
+      // Skip stale entries that were superseded by a cheaper route.
+      if cost > self.costs[span_index] {
+        continue;
+      }
+      // The span may have become impassable.
+      let Some(step_cost) =
+        span_cost(compact_heightfield, area_cost, span_index)
+      else {
+        continue;
+      };
+
+      let position = graph.position(span_index);
+      for neighbour_index in graph.neighbours(span_index, passable) {
+        let neighbour_position = graph.position(neighbour_index);
+        let neighbour_cost =
+          cost + distance(position, neighbour_position) * step_cost;
+        if neighbour_cost >= self.costs[neighbour_index] {
+          continue;
+        }
+
+        self.costs[neighbour_index] = neighbour_cost;
+        self.next_spans[neighbour_index] = span_index;
+        let delta_x = position.x - neighbour_position.x;
+        let delta_z = position.z - neighbour_position.z;
+        let length = (delta_x * delta_x + delta_z * delta_z).sqrt();
+        self.directions[neighbour_index] =
+          Vec3::new(delta_x / length, 0.0, delta_z / length);
+        open
+          .push(OpenNode { cost: neighbour_cost, span_index: neighbour_index });
+      }
+    }
+  }
+
+  // Returns the integration field: the cost to reach the nearest target from
+  // each span (infinite if no target can be reached).
+  pub fn costs(&self) -> &[f32] {
+    &self.costs
+  }
+
+  // Returns the span to move to from `span_index`, or None if `span_index` is
+  // a target or cannot reach a target.
+  pub fn next_span(&self, span_index: usize) -> Option<usize> {
+    match self.next_spans[span_index] {
+      usize::MAX => None,
+      next_span => Some(next_span),
+    }
+  }
+
+  // Returns the normalized XZ direction to move in from `span_index`, or None
+  // if `span_index` is a target or cannot reach a target.
+  pub fn direction(&self, span_index: usize) -> Option<Vec3<f32>> {
+    self.next_span(span_index).map(|_| self.directions[span_index])
+  }
+
+  // Returns the normalized XZ direction to move in from the reachable floor
+  // closest to `position`. See `direction`.
+  pub fn direction_at(
+    &self,
+    compact_heightfield: &CompactHeightfield<impl CompactHeightfieldState>,
+    position: Vec3<f32>,
+  ) -> Option<Vec3<f32>> {
+    let span_index =
+      span_graph::closest_floor(compact_heightfield, position, |span_index| {
+        self.costs[span_index].is_finite()
+      })?;
+    self.direction(span_index)
+  }
+}
+
+// Returns the cost of travelling one world unit on `span_index`.
+fn span_cost<TypeState: CompactHeightfieldState>(
+  compact_heightfield: &CompactHeightfield<TypeState>,
+  area_cost: &impl Fn(u8) -> Option<f32>,
+  span_index: usize,
+) -> Option<f32> {
+  match compact_heightfield.span_areas()[span_index] {
+    INVALID_AREA_ID => None,
+    area_id => area_cost(area_id),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    test_util::flat_compact_heightfield, Context, FlowField, Vec3,
+    WALKABLE_AREA_ID,
+  };
+
+  fn area_cost(area_id: u8) -> Option<f32> {
+    if area_id == 1 {
+      None
+    } else {
+      Some(1.0)
+    }
+  }
+
+  #[test]
+  fn flows_to_target() {
+    let mut context = Context::new();
+    let compact_heightfield = flat_compact_heightfield(&mut context, 5.0);
+
+    let flow_field = FlowField::new(
+      &compact_heightfield,
+      &[Vec3::new(4.5, 1.0, 2.5)],
+      area_cost,
+    );
+
+    assert_eq!(flow_field.costs()[14], 0.0);
+    assert_eq!(flow_field.direction(14), None);
+    assert_eq!(flow_field.costs()[10], 4.0);
+    assert_eq!(
+      flow_field.direction_at(&compact_heightfield, Vec3::new(0.5, 1.0, 2.5)),
+      Some(Vec3::new(1.0, 0.0, 0.0))
+    );
+    assert_eq!(
+      flow_field.direction_at(&compact_heightfield, Vec3::new(3.5, 1.0, 1.5)),
+      Some(Vec3::new(0.5f32.sqrt(), 0.0, 0.5f32.sqrt()))
+    );
+    assert_eq!(
+      flow_field.direction_at(&compact_heightfield, Vec3::new(6.5, 1.0, 2.5)),
+      None
+    );
+  }
+
+  #[test]
+  fn updates_region() {
+    let mut context = Context::new();
+    let mut compact_heightfield = flat_compact_heightfield(&mut context, 5.0);
+
+    let targets = [Vec3::new(4.5, 1.0, 2.5)];
+    let mut flow_field =
+      FlowField::new(&compact_heightfield, &targets, area_cost);
+
+    // A wall along x = 2 with a gap at z = 0.
+    let wall_min_bounds = Vec3::new(2.0, 0.0, 1.0);
+    let wall_max_bounds = Vec3::new(2.99, 2.0, 5.0);
+    compact_heightfield.mark_box_area_with_id(
+      &mut context,
+      wall_min_bounds,
+      wall_max_bounds,
+      1,
+    );
+    flow_field.update_region(
+      &compact_heightfield,
+      wall_min_bounds,
+      wall_max_bounds,
+      area_cost,
+    );
+
+    let expected_flow_field =
+      FlowField::new(&compact_heightfield, &targets, area_cost);
+    assert_eq!(flow_field.costs(), expected_flow_field.costs());
+    assert_eq!(flow_field.costs()[12], f32::INFINITY);
+    assert_eq!(
+      flow_field.direction_at(&compact_heightfield, Vec3::new(1.5, 1.0, 0.5)),
+      Some(Vec3::new(1.0, 0.0, 0.0))
+    );
+
+    // Removing the wall makes spans cheaper again.
+    compact_heightfield.mark_box_area_with_id(
+      &mut context,
+      wall_min_bounds,
+      wall_max_bounds,
+      WALKABLE_AREA_ID,
+    );
+    flow_field.update_region(
+      &compact_heightfield,
+      wall_min_bounds,
+      wall_max_bounds,
+      area_cost,
+    );
+
+    let expected_flow_field =
+      FlowField::new(&compact_heightfield, &targets, area_cost);
+    assert_eq!(flow_field.costs(), expected_flow_field.costs());
+  }
+}
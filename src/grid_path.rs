@@ -0,0 +1,179 @@
+use std::collections::BinaryHeap;
+
+use crate::{
+  span_graph::{self, distance, OpenNode, SpanGraph},
+  CompactHeightfield, CompactHeightfieldState, Vec3, INVALID_AREA_ID,
+};
+
+// A path found by `CompactHeightfield::find_grid_path`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct GridPath {
+  // The world positions of the floors of the spans along the path (at the
+  // centre of each cell), from the start to the end.
+  pub points: Vec<Vec3<f32>>,
+  // The total cost of the path. This is the length of each step multiplied by
+  // the cost of the area being stepped onto.
+  pub cost: f32,
+}
+
+impl GridPath {
+  // The world length of the path.
+  pub fn length(&self) -> f32 {
+    self.points.windows(2).map(|points| distance(points[0], points[1])).sum()
+  }
+}
+
+pub(crate) fn find_grid_path<TypeState: CompactHeightfieldState>(
+  compact_heightfield: &CompactHeightfield<TypeState>,
+  start: Vec3<f32>,
+  end: Vec3<f32>,
+  area_cost: impl Fn(u8) -> Option<f32>,
+) -> Option<GridPath> {
+  let areas = compact_heightfield.span_areas();
+  let span_cost = |span_index: usize| match areas[span_index] {
+    INVALID_AREA_ID => None,
+    area_id => area_cost(area_id),
+  };
+  let passable = |span_index: usize| span_cost(span_index).is_some();
+
+  let start_span =
+    span_graph::closest_floor(compact_heightfield, start, passable)?;
+  let end_span = span_graph::closest_floor(compact_heightfield, end, passable)?;
+
+  let graph = SpanGraph::new(compact_heightfield);
+  let end_position = graph.position(end_span);
+
+  let mut costs = vec![f32::INFINITY; compact_heightfield.spans_len()];
+  let mut parents = vec![usize::MAX; compact_heightfield.spans_len()];
+  let mut open = BinaryHeap::new();
+
+  costs[start_span] = 0.0;
+  open.push(OpenNode {
+    cost: distance(graph.position(start_span), end_position),
+    span_index: start_span,
+  });
+
+  while let Some(OpenNode { cost: estimated_cost, span_index }) = open.pop() {
+    if span_index == end_span {
+      break;
+    }
+
+    let position = graph.position(span_index);
+    // Skip stale entries that were superseded by a cheaper path.
+    if estimated_cost > costs[span_index] + distance(position, end_position) {
+      continue;
+    }
+
+    for neighbour_index in graph.neighbours(span_index, passable) {
+      let neighbour_position = graph.position(neighbour_index);
+      let neighbour_cost = costs[span_index]
+        + distance(position, neighbour_position)
+          * span_cost(neighbour_index).unwrap();
+      if neighbour_cost >= costs[neighbour_index] {
+        continue;
+      }
+
+      costs[neighbour_index] = neighbour_cost;
+      parents[neighbour_index] = span_index;
+      open.push(OpenNode {
+        cost: neighbour_cost + distance(neighbour_position, end_position),
+        span_index: neighbour_index,
+      });
+    }
+  }
+
+  if costs[end_span].is_infinite() {
+    return None;
+  }
+
+  let mut points = vec![end_position];
+  let mut span_index = end_span;
+  while span_index != start_span {
+    span_index = parents[span_index];
+    points.push(graph.position(span_index));
+  }
+  points.reverse();
+
+  Some(GridPath { points, cost: costs[end_span] })
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{test_util::flat_compact_heightfield, Context, Vec3};
+
+  #[test]
+  fn finds_diagonal_path() {
+    let mut context = Context::new();
+    let compact_heightfield = flat_compact_heightfield(&mut context, 5.0);
+
+    let path = compact_heightfield
+      .find_grid_path(
+        Vec3::new(0.5, 1.0, 0.5),
+        Vec3::new(4.5, 1.0, 4.5),
+        |_| Some(1.0),
+      )
+      .expect("path found");
+
+    assert_eq!(
+      path.points,
+      [
+        Vec3::new(0.5, 1.0, 0.5),
+        Vec3::new(1.5, 1.0, 1.5),
+        Vec3::new(2.5, 1.0, 2.5),
+        Vec3::new(3.5, 1.0, 3.5),
+        Vec3::new(4.5, 1.0, 4.5),
+      ]
+    );
+    assert_eq!(path.cost, path.length());
+    assert!((path.length() - 4.0 * 2.0f32.sqrt()).abs() < 1e-5);
+  }
+
+  #[test]
+  fn avoids_expensive_and_impassable_areas() {
+    let mut context = Context::new();
+    let mut compact_heightfield = flat_compact_heightfield(&mut context, 5.0);
+
+    // A wall along x = 2, except for the bottom row which is expensive.
+    compact_heightfield.mark_box_area_with_id(
+      &mut context,
+      Vec3::new(2.0, 0.0, 1.0),
+      Vec3::new(2.99, 2.0, 5.0),
+      1,
+    );
+    compact_heightfield.mark_box_area_with_id(
+      &mut context,
+      Vec3::new(2.0, 0.0, 0.0),
+      Vec3::new(2.99, 2.0, 0.99),
+      2,
+    );
+
+    let area_cost = |area_id| match area_id {
+      1 => None,
+      2 => Some(10.0),
+      _ => Some(1.0),
+    };
+
+    let path = compact_heightfield
+      .find_grid_path(
+        Vec3::new(0.5, 1.0, 2.5),
+        Vec3::new(4.5, 1.0, 2.5),
+        area_cost,
+      )
+      .expect("path found");
+
+    assert_eq!(path.points[0], Vec3::new(0.5, 1.0, 2.5));
+    assert!(path.points.contains(&Vec3::new(2.5, 1.0, 0.5)));
+    assert_eq!(path.points[path.points.len() - 1], Vec3::new(4.5, 1.0, 2.5));
+    assert!(path.cost > path.length());
+
+    // Making the only gap impassable disconnects the two sides.
+    assert_eq!(
+      compact_heightfield.find_grid_path(
+        Vec3::new(0.5, 1.0, 2.5),
+        Vec3::new(4.5, 1.0, 2.5),
+        |area_id| if area_id == 1 || area_id == 2 { None } else { Some(1.0) },
+      ),
+      None
+    );
+  }
+}
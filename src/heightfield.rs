@@ -1,12 +1,23 @@
-use std::ops::DerefMut;
+use std::{
+  collections::BTreeMap,
+  ops::{DerefMut, Range},
+};
 
 use recastnavigation_sys::{
-  rcCalcGridSize, rcCreateHeightfield, rcFilterLedgeSpans,
+  rcAddSpan, rcCalcGridSize, rcCreateHeightfield, rcFilterLedgeSpans,
   rcFilterLowHangingWalkableObstacles, rcFilterWalkableLowHeightSpans,
-  rcRasterizeTriangles, rcRasterizeTriangles1, rcRasterizeTriangles2,
+  rcRasterizeTriangle, rcRasterizeTriangles, rcRasterizeTriangles1,
+  rcRasterizeTriangles2, rcSpan, RC_SPAN_MAX_HEIGHT,
 };
 
-use crate::{wrappers, Context, Vec3};
+use crate::{
+  compact_heightfield::Direction,
+  nav_scene, sampling,
+  shape::{self, ColumnRect},
+  tri_mesh, wrappers, AffineTransform, Context, Heightmap, LocalOrigin,
+  NavScene, SampleMode, SolidSpan, TriMesh, TriangleIndex, Vec3,
+  VertexPositions, WALKABLE_AREA_ID,
+};
 
 // A Recast heightfield. This essentially contains a voxelized model of the
 // "solid" parts of the world.
@@ -146,6 +157,277 @@ impl Heightfield {
     self.span((grid_x + grid_y * self.grid_width()) as usize)
   }
 
+  // Returns the solid spans in the column containing the world position `x` and
+  // `z`, ordered from lowest to highest. The tops of the spans are determined
+  // by `sample_mode`; everything else is taken from the containing column.
+  // Returns nothing if the position is outside the grid.
+  pub fn solid_spans_at(
+    &self,
+    x: f32,
+    z: f32,
+    sample_mode: SampleMode,
+  ) -> Vec<SolidSpan> {
+    let min_bounds = self.min_bounds();
+    let cell_horizontal_size = self.cell_horizontal_size();
+    let Some((cell_x, cell_z)) = sampling::containing_cell(
+      min_bounds,
+      cell_horizontal_size,
+      self.grid_width(),
+      self.grid_height(),
+      x,
+      z,
+    ) else {
+      return Vec::new();
+    };
+
+    let column_heights = |column_x: i32, column_z: i32| {
+      if column_x < 0
+        || column_x >= self.grid_width()
+        || column_z < 0
+        || column_z >= self.grid_height()
+      {
+        return Vec::new();
+      }
+      HeightfieldSpan::collect(self.span_by_grid(column_x, column_z))
+        .iter()
+        .map(|span| span.height_max_f32())
+        .collect()
+    };
+
+    let spans = HeightfieldSpan::collect(self.span_by_grid(cell_x, cell_z));
+    spans
+      .iter()
+      .enumerate()
+      .map(|(index, span)| SolidSpan {
+        min_height: span.height_min_f32(),
+        max_height: sampling::sample_height(
+          sample_mode,
+          min_bounds,
+          cell_horizontal_size,
+          x,
+          z,
+          span.height_max_f32(),
+          column_heights,
+        ),
+        clearance: spans.get(index + 1).map_or(f32::INFINITY, |next_span| {
+          next_span.height_min_f32() - span.height_max_f32()
+        }),
+        area_id: span.area_id() as u8,
+      })
+      .collect()
+  }
+
+  // Calls `visitor` on every span in the heightfield, allowing it to change the
+  // area ID of the span. This allows writing custom filters like the
+  // `filter_*` passes. Changes are only applied once every span has been
+  // visited, so `visitor` always sees the original area IDs of the span and
+  // its neighbours regardless of the order spans are visited in.
+  pub fn visit_spans_mut(
+    &mut self,
+    mut visitor: impl FnMut(&mut HeightfieldSpanMut),
+  ) {
+    // The column index, the index of the span in the column, and the new area
+    // ID of each changed span.
+    let mut changed_spans = Vec::new();
+    for (column_index, column_head) in self.spans_iter().enumerate() {
+      let grid_x = column_index as i32 % self.grid_width();
+      let grid_z = column_index as i32 / self.grid_width();
+      for (span_index, span) in
+        HeightfieldSpan::collect(column_head).into_iter().enumerate()
+      {
+        let original_area_id = span.area_id() as u8;
+        let mut span_mut = HeightfieldSpanMut {
+          span,
+          grid_x,
+          grid_z,
+          area_id: original_area_id,
+        };
+        visitor(&mut span_mut);
+        if span_mut.area_id != original_area_id {
+          changed_spans.push((column_index, span_index, span_mut.area_id));
+        }
+      }
+    }
+
+    for (column_index, span_index, area_id) in changed_spans {
+      // SAFETY: `self.heightfield.spans` has exactly width * height entries and
+      // `column_index` is in range. The column has at least `span_index` + 1
+      // spans since it has not changed since it was visited. The heightfield
+      // is exclusively borrowed so no HeightfieldSpans reference the span.
+      unsafe {
+        let mut span = *self.heightfield.spans.add(column_index);
+        for _ in 0..span_index {
+          span = (*span).next;
+        }
+        (*span).set_area(area_id as u32);
+      }
+    }
+  }
+
+  // Returns the index of the column at the provided grid coordinates. Panics
+  // if the coordinates are outside the grid.
+  fn column_index(&self, grid_x: i32, grid_z: i32) -> usize {
+    assert!(
+      0 <= grid_x
+        && grid_x < self.grid_width()
+        && 0 <= grid_z
+        && grid_z < self.grid_height(),
+      "Grid coordinates out-of-bounds. x={}, z={}, grid_width={}, grid_height={}",
+      grid_x,
+      grid_z,
+      self.grid_width(),
+      self.grid_height()
+    );
+    (grid_x + grid_z * self.grid_width()) as usize
+  }
+
+  // Adds a solid span covering `heights` (in cells above `min_bounds`) to the
+  // column at the provided grid coordinates, as if it were rasterized from
+  // triangles. Overlapping spans in the column are merged with the new span,
+  // and their area IDs are merged according to `flag_merge_threshold` (see
+  // `rasterize_triangles`). Panics if the grid coordinates are outside the
+  // grid or `heights` is empty or out of range.
+  pub fn add_span(
+    &mut self,
+    context: &mut Context,
+    grid_x: i32,
+    grid_z: i32,
+    heights: Range<u16>,
+    area_id: u8,
+    flag_merge_threshold: i32,
+  ) -> Result<(), ()> {
+    self.column_index(grid_x, grid_z);
+    assert!(
+      !heights.is_empty() && heights.end as i32 <= RC_SPAN_MAX_HEIGHT,
+      "Span heights are invalid. heights={:?}",
+      heights
+    );
+
+    // SAFETY: rcAddSpan only mutates `context.context` and `self.heightfield`
+    // which are both passed by exclusive borrows. The grid coordinates have
+    // been checked to be in the grid.
+    let span_added = unsafe {
+      rcAddSpan(
+        context.context.deref_mut(),
+        self.heightfield.deref_mut(),
+        grid_x,
+        grid_z,
+        heights.start,
+        heights.end,
+        area_id,
+        flag_merge_threshold,
+      )
+    };
+
+    if span_added {
+      Ok(())
+    } else {
+      Err(())
+    }
+  }
+
+  // Removes the solid parts of the column at the provided grid coordinates
+  // covered by `heights` (in cells above `min_bounds`). Spans inside the range
+  // are removed, spans partially inside the range are trimmed, and spans
+  // containing the range are split in two (which may need to allocate a new
+  // span, hence the Result). If allocating fails, the column is left
+  // unchanged. Panics if the grid coordinates are outside the grid or
+  // `heights` is empty.
+  pub fn remove_spans(
+    &mut self,
+    context: &mut Context,
+    grid_x: i32,
+    grid_z: i32,
+    heights: Range<u16>,
+  ) -> Result<(), ()> {
+    let column_index = self.column_index(grid_x, grid_z);
+    assert!(
+      !heights.is_empty(),
+      "Span heights are invalid. heights={:?}",
+      heights
+    );
+    let (height_min, height_max) = (heights.start as u32, heights.end as u32);
+
+    let mut split_span = None;
+    // SAFETY: `self.heightfield.spans` has exactly width * height entries and
+    // `column_index` is in range. Every span in the column is allocated from
+    // the heightfield's pools, and the heightfield is exclusively borrowed so
+    // no HeightfieldSpans reference the spans being modified.
+    unsafe {
+      let mut link = self.heightfield.spans.add(column_index);
+      while let Some(span) = (*link).as_mut() {
+        let (span_min, span_max) = (span.smin(), span.smax());
+        if span_max <= height_min || height_max <= span_min {
+          // The span is outside the range.
+        } else if height_min <= span_min && span_max <= height_max {
+          *link = span.next;
+          self.free_span(span);
+          continue;
+        } else if span_min < height_min && height_max < span_max {
+          // No other span can overlap the range, so only the split is left.
+          // The span is trimmed to its lower part before the upper part is
+          // added so the two are not merged.
+          span.set_smax(height_min);
+          split_span = Some((span as *mut rcSpan, span_max));
+          break;
+        } else if span_min < height_min {
+          span.set_smax(height_min);
+        } else {
+          span.set_smin(height_max);
+        }
+        link = &mut span.next;
+      }
+    }
+
+    let (span, span_max) = match split_span {
+      None => return Ok(()),
+      Some(split_span) => split_span,
+    };
+    // SAFETY: `span` is still in the column, since nothing has been freed
+    // since it was found.
+    let area_id = unsafe { (*span).area() } as u8;
+    let result = self.add_span(
+      context,
+      grid_x,
+      grid_z,
+      heights.end..span_max as u16,
+      area_id,
+      0,
+    );
+    if result.is_err() {
+      // SAFETY: rcAddSpan only fails before it modifies the column, so `span`
+      // is still in the column.
+      unsafe { (*span).set_smax(span_max) };
+    }
+    result
+  }
+
+  // Removes all spans in the column at the provided grid coordinates. Panics
+  // if the grid coordinates are outside the grid.
+  pub fn clear_column(&mut self, grid_x: i32, grid_z: i32) {
+    let column_index = self.column_index(grid_x, grid_z);
+    // SAFETY: See `remove_spans`.
+    unsafe {
+      let link = self.heightfield.spans.add(column_index);
+      while let Some(span) = (*link).as_mut() {
+        *link = span.next;
+        self.free_span(span);
+      }
+    }
+  }
+
+  // Returns `span` to the heightfield's free list so it can be reused.
+  // SAFETY: `span` must be allocated from this heightfield's pools and must
+  // not be reachable from any column.
+  unsafe fn free_span(&mut self, span: *mut rcSpan) {
+    // SAFETY: `span` is valid (due to the function safety guarantee). This
+    // matches how Recast frees spans internally.
+    unsafe {
+      (*span).next = self.heightfield.freelist;
+    }
+    self.heightfield.freelist = span;
+  }
+
   // Rasterizes triangles into the heightfield. `vertices` must come in
   // triangles (in groups of 3). `area_ids` must hold one ID per triangle
   // (i.e. `area_ids.len() == vertices.len() / 3`). `flag_merge_threshold`
@@ -281,6 +563,80 @@ impl Heightfield {
     }
   }
 
+  // Rasterizes triangles into the heightfield, reading vertex positions
+  // directly from `vertices` (which may be interleaved with other vertex data)
+  // without copying them. `indices` must come in triangles (in groups of 3) of
+  // any TriangleIndex type. `area_ids` must hold one ID per triangle. See
+  // `rasterize_triangles` for the meaning of `flag_merge_threshold`. Panics if
+  // any triangle indices are out of range.
+  pub fn rasterize_indexed_triangles<Index: TriangleIndex>(
+    &mut self,
+    context: &mut Context,
+    vertices: VertexPositions,
+    indices: &[Index],
+    area_ids: &[u8],
+    flag_merge_threshold: i32,
+  ) -> Result<(), ()> {
+    assert_eq!(
+      indices.len() % 3,
+      0,
+      "Indices must come in triangles (groups of 3). Index count: {}",
+      indices.len()
+    );
+    assert_eq!(
+      indices.len() / 3,
+      area_ids.len(),
+      "area_ids should have one entry per triangle."
+    );
+
+    // Check every triangle before rasterizing any of them.
+    let vertex_index = |index: Index| {
+      index.to_usize().filter(|&vertex_index| vertex_index < vertices.len())
+    };
+    let triangles = indices
+      .chunks_exact(3)
+      .map(|triangle| {
+        match [
+          vertex_index(triangle[0]),
+          vertex_index(triangle[1]),
+          vertex_index(triangle[2]),
+        ] {
+          [Some(index_0), Some(index_1), Some(index_2)] => {
+            [index_0, index_1, index_2]
+          }
+          _ => panic!(
+            "Triangle indexes out-of-bounds vertex. Triangle={:?}, vertices_len={}",
+            triangle,
+            vertices.len()
+          ),
+        }
+      })
+      .collect::<Vec<_>>();
+
+    for (triangle, &area_id) in triangles.iter().zip(area_ids) {
+      let [vertex_0, vertex_1, vertex_2] =
+        triangle.map(|index| vertices.get(index));
+      // SAFETY: rcRasterizeTriangle only mutates `context.context` and
+      // `self.heightfield` which are both passed by exclusive borrows. Each
+      // vertex is only read as 3 floats, which matches Vec3.
+      let rasterized_triangle = unsafe {
+        rcRasterizeTriangle(
+          context.context.deref_mut(),
+          &vertex_0.x,
+          &vertex_1.x,
+          &vertex_2.x,
+          area_id,
+          self.heightfield.deref_mut(),
+          flag_merge_threshold,
+        )
+      };
+      if !rasterized_triangle {
+        return Err(());
+      }
+    }
+    Ok(())
+  }
+
   // Same as `rasterize_indexed_triangles_u16_unchecked`, but panics if any
   // triangle indices are out of range.
   pub fn rasterize_indexed_triangles_u16(
@@ -325,19 +681,7 @@ impl Heightfield {
     area_ids: &[u8],
     flag_merge_threshold: i32,
   ) -> Result<(), ()> {
-    for triangle in triangles {
-      assert!(
-        0 <= triangle.x
-          && triangle.x < vertices.len() as i32
-          && 0 <= triangle.y
-          && triangle.y < vertices.len() as i32
-          && 0 <= triangle.z
-          && triangle.z < vertices.len() as i32,
-        "Triangle indexes out-of-bounds vertex. Triangle={:?}, vertices_len={}",
-        *triangle,
-        vertices.len()
-      );
-    }
+    tri_mesh::assert_triangle_indices(vertices, triangles);
 
     // SAFETY: We have checked that all indices in `triangles` are valid.
     // Therefore, the function is guaranteed to be safe.
@@ -352,6 +696,399 @@ impl Heightfield {
     }
   }
 
+  // Rasterizes the triangles of `tri_mesh`. Since TriMesh validates its
+  // indices, they are not checked again. See
+  // `rasterize_indexed_triangles_i32_unchecked`.
+  pub fn rasterize_tri_mesh(
+    &mut self,
+    context: &mut Context,
+    tri_mesh: &TriMesh,
+    area_ids: &[u8],
+    flag_merge_threshold: i32,
+  ) -> Result<(), ()> {
+    // SAFETY: TriMesh checks that all its triangle indices are in range.
+    unsafe {
+      self.rasterize_indexed_triangles_i32_unchecked(
+        context,
+        tri_mesh.vertices(),
+        tri_mesh.triangles(),
+        area_ids,
+        flag_merge_threshold,
+      )
+    }
+  }
+
+  // Rasterizes a solid box centred on the origin with `half_extents`, placed by
+  // `transform`. Unlike rasterizing the triangles of the box, this fills each
+  // column from the bottom of the box to its top. See `rasterize_triangles`
+  // for the meaning of `flag_merge_threshold`.
+  pub fn rasterize_box(
+    &mut self,
+    context: &mut Context,
+    half_extents: Vec3<f32>,
+    transform: &AffineTransform,
+    area_id: u8,
+    flag_merge_threshold: i32,
+  ) -> Result<(), ()> {
+    let (vertices, triangles) = shape::box_triangles(half_extents);
+    let vertices = vertices.map(|vertex| transform.transform_point(vertex));
+    self.rasterize_convex_triangles(
+      context,
+      &vertices,
+      triangles.map(|triangle| [triangle.x, triangle.y, triangle.z]).as_slice(),
+      area_id,
+      flag_merge_threshold,
+    )
+  }
+
+  // Rasterizes a solid sphere centred on the origin with `radius`, placed by
+  // `transform`. Only the translation of `transform` is used, so the sphere is
+  // never scaled. Each column is filled exactly from the bottom of the sphere
+  // to its top. See `rasterize_triangles` for the meaning of
+  // `flag_merge_threshold`.
+  pub fn rasterize_sphere(
+    &mut self,
+    context: &mut Context,
+    radius: f32,
+    transform: &AffineTransform,
+    area_id: u8,
+    flag_merge_threshold: i32,
+  ) -> Result<(), ()> {
+    let center = transform.translation;
+    self.rasterize_columns(
+      context,
+      Vec3::new(center.x - radius, center.y - radius, center.z - radius),
+      Vec3::new(center.x + radius, center.y + radius, center.z + radius),
+      area_id,
+      flag_merge_threshold,
+      |rect| shape::sphere_column_heights(center, radius, rect),
+    )
+  }
+
+  // Rasterizes a solid capsule centred on the origin, placed by `transform`.
+  // Before transforming, the axis of the capsule is along Y from
+  // -`half_height` to `half_height`, and `radius` is the radius of the capsule
+  // around its axis. Only the axis is transformed, so `radius` is never
+  // scaled. See `rasterize_triangles` for the meaning of
+  // `flag_merge_threshold`.
+  pub fn rasterize_capsule(
+    &mut self,
+    context: &mut Context,
+    half_height: f32,
+    radius: f32,
+    transform: &AffineTransform,
+    area_id: u8,
+    flag_merge_threshold: i32,
+  ) -> Result<(), ()> {
+    let start = transform.transform_point(Vec3::new(0.0, -half_height, 0.0));
+    let end = transform.transform_point(Vec3::new(0.0, half_height, 0.0));
+    self.rasterize_columns(
+      context,
+      Vec3::new(
+        start.x.min(end.x) - radius,
+        start.y.min(end.y) - radius,
+        start.z.min(end.z) - radius,
+      ),
+      Vec3::new(
+        start.x.max(end.x) + radius,
+        start.y.max(end.y) + radius,
+        start.z.max(end.z) + radius,
+      ),
+      area_id,
+      flag_merge_threshold,
+      |rect| shape::capsule_column_heights(start, end, radius, rect),
+    )
+  }
+
+  // Rasterizes a solid convex hull placed by `transform`. `triangles` contains
+  // i32 Vec3's, where x,y,z corresponds to indices of `vertices`, and must
+  // cover the surface of the hull (e.g. the triangles of a convex collider).
+  // Each column is filled from the bottom of the hull to its top. See
+  // `rasterize_triangles` for the meaning of `flag_merge_threshold`. Panics if
+  // any triangle indices are out of range.
+  pub fn rasterize_convex_hull(
+    &mut self,
+    context: &mut Context,
+    vertices: &[Vec3<f32>],
+    triangles: &[Vec3<i32>],
+    transform: &AffineTransform,
+    area_id: u8,
+    flag_merge_threshold: i32,
+  ) -> Result<(), ()> {
+    tri_mesh::assert_triangle_indices(vertices, triangles);
+    let triangles = triangles
+      .iter()
+      .map(|triangle| {
+        [triangle.x as usize, triangle.y as usize, triangle.z as usize]
+      })
+      .collect::<Vec<_>>();
+    let vertices = vertices
+      .iter()
+      .map(|&vertex| transform.transform_point(vertex))
+      .collect::<Vec<_>>();
+    self.rasterize_convex_triangles(
+      context,
+      &vertices,
+      &triangles,
+      area_id,
+      flag_merge_threshold,
+    )
+  }
+
+  // Rasterizes terrain described by `heightmap` without converting it to
+  // triangles. The terrain is the bilinear surface between the samples, and
+  // each column is filled from the lowest to the highest point of the surface
+  // within the column. Quads steeper than `walkable_slope_angle` (in degrees)
+  // are marked with `INVALID_AREA_ID`. See `rasterize_triangles` for the
+  // meaning of `flag_merge_threshold`. Panics if the sizes of the heightmap's
+  // slices do not match its number of samples.
+  pub fn rasterize_heightmap(
+    &mut self,
+    context: &mut Context,
+    heightmap: &Heightmap,
+    walkable_slope_angle: f32,
+    flag_merge_threshold: i32,
+  ) -> Result<(), ()> {
+    for quad in heightmap.quads(walkable_slope_angle) {
+      self.rasterize_columns(
+        context,
+        quad.min_bounds(),
+        quad.max_bounds(),
+        quad.area_id,
+        flag_merge_threshold,
+        |rect| quad.column_heights(rect),
+      )?;
+    }
+    Ok(())
+  }
+
+  // Rasterizes a point cloud (e.g. from a LiDAR scan). Each point is treated as
+  // the top of a solid `thickness` thick. Within a column, points whose solids
+  // are less than `merge_distance` apart are merged into a single span, taking
+  // the area ID of the highest point in the span. `area_ids` must hold one ID
+  // per point. Points outside the heightfield or with NaN or infinite
+  // coordinates are ignored. See `rasterize_triangles` for the meaning of
+  // `flag_merge_threshold`.
+  pub fn rasterize_point_cloud(
+    &mut self,
+    context: &mut Context,
+    points: &[Vec3<f32>],
+    area_ids: &[u8],
+    thickness: f32,
+    merge_distance: f32,
+    flag_merge_threshold: i32,
+  ) -> Result<(), ()> {
+    assert_eq!(
+      points.len(),
+      area_ids.len(),
+      "area_ids should have one entry per point."
+    );
+
+    let min_bounds = self.min_bounds();
+    let cell_horizontal_size = self.cell_horizontal_size();
+    let mut column_points = BTreeMap::<(i32, i32), Vec<(f32, u8)>>::new();
+    for (point, &area_id) in points.iter().zip(area_ids) {
+      if !point.x.is_finite() || !point.y.is_finite() || !point.z.is_finite() {
+        continue;
+      }
+      let Some(cell) = sampling::containing_cell(
+        min_bounds,
+        cell_horizontal_size,
+        self.grid_width(),
+        self.grid_height(),
+        point.x,
+        point.z,
+      ) else {
+        continue;
+      };
+      column_points.entry(cell).or_default().push((point.y, area_id));
+    }
+
+    for ((grid_x, grid_z), mut points) in column_points {
+      points.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+      // The merged solids in the column as (bottom, top, area ID).
+      let mut solids: Vec<(f32, f32, u8)> = Vec::new();
+      for (height, area_id) in points {
+        match solids.last_mut() {
+          Some(solid) if height - thickness - solid.1 < merge_distance => {
+            // Points are sorted, so this point is the highest in the solid.
+            solid.1 = height;
+            solid.2 = area_id;
+          }
+          _ => solids.push((height - thickness, height, area_id)),
+        }
+      }
+
+      for (low, high, area_id) in solids {
+        let Some(heights) = self.span_heights(low, high) else {
+          continue;
+        };
+        self.add_span(
+          context,
+          grid_x,
+          grid_z,
+          heights,
+          area_id,
+          flag_merge_threshold,
+        )?;
+      }
+    }
+    Ok(())
+  }
+
+  // Rasterizes the included instances of `scene`. Instances are transformed
+  // and rasterized one at a time, so the transformed vertices of the whole
+  // scene are never held at once. Triangles steeper than
+  // `walkable_slope_angle` are marked with `INVALID_AREA_ID` unless overridden
+  // by the instance. See `rasterize_triangles` for the meaning of
+  // `flag_merge_threshold`. Panics if an instance refers to a mesh that is not
+  // in the scene.
+  pub fn rasterize_scene(
+    &mut self,
+    context: &mut Context,
+    scene: &NavScene,
+    walkable_slope_angle: f32,
+    flag_merge_threshold: i32,
+  ) -> Result<(), ()> {
+    nav_scene::rasterize_scene(
+      self,
+      context,
+      scene,
+      &LocalOrigin::new(Vec3::new(0.0, 0.0, 0.0)),
+      walkable_slope_angle,
+      flag_merge_threshold,
+    )
+  }
+
+  // Rasterizes the convex solid bounded by `triangles` (which index into
+  // `vertices`).
+  fn rasterize_convex_triangles(
+    &mut self,
+    context: &mut Context,
+    vertices: &[Vec3<f32>],
+    triangles: &[[usize; 3]],
+    area_id: u8,
+    flag_merge_threshold: i32,
+  ) -> Result<(), ()> {
+    let Some(&first_vertex) = vertices.first() else {
+      return Ok(());
+    };
+    let (mut min_bounds, mut max_bounds) = (first_vertex, first_vertex);
+    for vertex in vertices {
+      min_bounds = Vec3::new(
+        min_bounds.x.min(vertex.x),
+        min_bounds.y.min(vertex.y),
+        min_bounds.z.min(vertex.z),
+      );
+      max_bounds = Vec3::new(
+        max_bounds.x.max(vertex.x),
+        max_bounds.y.max(vertex.y),
+        max_bounds.z.max(vertex.z),
+      );
+    }
+
+    let triangles = triangles
+      .iter()
+      .map(|triangle| triangle.map(|index| vertices[index]))
+      .collect::<Vec<_>>();
+    self.rasterize_columns(
+      context,
+      min_bounds,
+      max_bounds,
+      area_id,
+      flag_merge_threshold,
+      |rect| shape::convex_hull_column_heights(&triangles, rect),
+    )
+  }
+
+  // Adds a span to each column overlapping a shape with the provided bounds.
+  // `column_heights` returns the lowest and highest world heights of the shape
+  // within a column, or None if the shape does not overlap the column. Heights
+  // are converted to spans the same way Recast converts triangles.
+  fn rasterize_columns(
+    &mut self,
+    context: &mut Context,
+    shape_min_bounds: Vec3<f32>,
+    shape_max_bounds: Vec3<f32>,
+    area_id: u8,
+    flag_merge_threshold: i32,
+    column_heights: impl Fn(&ColumnRect) -> Option<(f32, f32)>,
+  ) -> Result<(), ()> {
+    let min_bounds = self.min_bounds();
+    let cell_horizontal_size = self.cell_horizontal_size();
+    if shape_max_bounds.y < min_bounds.y
+      || shape_min_bounds.y > self.max_bounds().y
+    {
+      return Ok(());
+    }
+
+    let to_cell = |position: f32, min_bound: f32, cells: i32| {
+      (((position - min_bound) / cell_horizontal_size).floor() as i32)
+        .clamp(0, cells - 1)
+    };
+    let min_x = to_cell(shape_min_bounds.x, min_bounds.x, self.grid_width());
+    let max_x = to_cell(shape_max_bounds.x, min_bounds.x, self.grid_width());
+    let min_z = to_cell(shape_min_bounds.z, min_bounds.z, self.grid_height());
+    let max_z = to_cell(shape_max_bounds.z, min_bounds.z, self.grid_height());
+
+    for grid_z in min_z..=max_z {
+      for grid_x in min_x..=max_x {
+        let rect = ColumnRect {
+          min_x: min_bounds.x + grid_x as f32 * cell_horizontal_size,
+          min_z: min_bounds.z + grid_z as f32 * cell_horizontal_size,
+          max_x: min_bounds.x + (grid_x + 1) as f32 * cell_horizontal_size,
+          max_z: min_bounds.z + (grid_z + 1) as f32 * cell_horizontal_size,
+        };
+        let Some((low, high)) = column_heights(&rect) else {
+          continue;
+        };
+
+        let Some(heights) = self.span_heights(low, high) else {
+          continue;
+        };
+        self.add_span(
+          context,
+          grid_x,
+          grid_z,
+          heights,
+          area_id,
+          flag_merge_threshold,
+        )?;
+      }
+    }
+    Ok(())
+  }
+
+  // Converts the world heights `low` and `high` to the span heights covering
+  // them, the same way Recast converts triangles. Returns None if the heights
+  // are outside the heightfield or NaN.
+  fn span_heights(&self, low: f32, high: f32) -> Option<Range<u16>> {
+    let min_height = self.min_bounds().y;
+    let max_height = self.max_bounds().y - min_height;
+    let cell_height = self.cell_height();
+
+    let (low, high) = (low - min_height, high - min_height);
+    if low.is_nan() || high.is_nan() || high < 0.0 || low > max_height {
+      return None;
+    }
+    let (low, high) = (low.max(0.0), high.min(max_height));
+
+    let span_min =
+      ((low / cell_height).floor() as i32).clamp(0, RC_SPAN_MAX_HEIGHT);
+    // Round up to at least one cell above `span_min`, so even a zero-height
+    // range produces a span.
+    let span_max = ((high / cell_height).ceil() as i32)
+      .max(span_min + 1)
+      .min(RC_SPAN_MAX_HEIGHT);
+    // Spans starting at the maximum span height would be empty, so they are
+    // skipped.
+    if span_min >= span_max {
+      return None;
+    }
+    Some(span_min as u16..span_max as u16)
+  }
+
   // Marks spans as walkable if they are within `walkable_climb` grid cells of a
   // neighbouring span. In other words, marks spans as walkable if an agent can
   // climb up the obstacle from a walkable span.
@@ -466,6 +1203,82 @@ impl<'heightfield> HeightfieldSpan<'heightfield> {
   }
 }
 
+// A span being visited by `Heightfield::visit_spans_mut`. This gives read
+// access to the span and the columns around it, and allows changing the area
+// ID of the span.
+pub struct HeightfieldSpanMut<'heightfield> {
+  span: HeightfieldSpan<'heightfield>,
+  grid_x: i32,
+  grid_z: i32,
+  area_id: u8,
+}
+
+impl<'heightfield> HeightfieldSpanMut<'heightfield> {
+  // The span being visited. This always has the original area ID of the span.
+  pub fn span(&self) -> HeightfieldSpan<'heightfield> {
+    self.span
+  }
+
+  // The grid coordinates of the column containing the span.
+  pub fn grid_x(&self) -> i32 {
+    self.grid_x
+  }
+
+  pub fn grid_z(&self) -> i32 {
+    self.grid_z
+  }
+
+  // The area ID the span will have once every span has been visited.
+  pub fn area_id(&self) -> u8 {
+    self.area_id
+  }
+
+  // Sets the area ID of the span. Panics if `area_id` is larger than
+  // `WALKABLE_AREA_ID` (spans cannot store larger area IDs).
+  pub fn set_area_id(&mut self, area_id: u8) {
+    assert!(
+      area_id <= WALKABLE_AREA_ID,
+      "Area ID is too large. area_id={}",
+      area_id
+    );
+    self.area_id = area_id;
+  }
+
+  // Returns the spans in the column containing the span, ordered from lowest
+  // to highest (including the span itself).
+  pub fn column(&self) -> Vec<HeightfieldSpan<'heightfield>> {
+    HeightfieldSpan::collect(
+      self.span.heightfield.span_by_grid(self.grid_x, self.grid_z),
+    )
+  }
+
+  // Returns the spans in the neighbouring column in `direction`, ordered from
+  // lowest to highest. Returns nothing if the neighbouring column is outside
+  // the grid.
+  pub fn neighbour_column(
+    &self,
+    direction: Direction,
+  ) -> Vec<HeightfieldSpan<'heightfield>> {
+    let (offset_x, offset_z) = match direction {
+      Direction::NegX => (-1, 0),
+      Direction::PosZ => (0, 1),
+      Direction::PosX => (1, 0),
+      Direction::NegZ => (0, -1),
+    };
+    let heightfield = self.span.heightfield;
+    let (neighbour_x, neighbour_z) =
+      (self.grid_x + offset_x, self.grid_z + offset_z);
+    if neighbour_x < 0
+      || neighbour_x >= heightfield.grid_width()
+      || neighbour_z < 0
+      || neighbour_z >= heightfield.grid_height()
+    {
+      return Vec::new();
+    }
+    HeightfieldSpan::collect(heightfield.span_by_grid(neighbour_x, neighbour_z))
+  }
+}
+
 impl<'hf> std::fmt::Debug for HeightfieldSpan<'hf> {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     f.debug_struct("HeightfieldSpan")
@@ -480,7 +1293,10 @@ impl<'hf> std::fmt::Debug for HeightfieldSpan<'hf> {
 mod tests {
   use std::panic::AssertUnwindSafe;
 
-  use crate::{Context, Heightfield, HeightfieldSpan, Vec3, WALKABLE_AREA_ID};
+  use crate::{
+    Context, Direction, Heightfield, HeightfieldSpan, SampleMode, SolidSpan,
+    Vec3, INVALID_AREA_ID, WALKABLE_AREA_ID,
+  };
 
   macro_rules! assert_span_column_eq {
       ($span_column: expr, $expected_column: expr) => {{
@@ -818,7 +1634,7 @@ mod tests {
         .next_span_in_column()
         .expect("ledge rasterized")
         .area_id(),
-      WALKABLE_AREA_ID as _
+      WALKABLE_AREA_ID as u32
     );
 
     // High ledge is still not walkable.
@@ -895,7 +1711,7 @@ mod tests {
         .next_span_in_column()
         .expect("ledge rasterized")
         .area_id(),
-      WALKABLE_AREA_ID as _
+      WALKABLE_AREA_ID as u32
     );
 
     heightfield.filter_ledge_spans(
@@ -923,7 +1739,7 @@ mod tests {
         .next_span_in_column()
         .expect("ledge rasterized")
         .area_id(),
-      WALKABLE_AREA_ID as _
+      WALKABLE_AREA_ID as u32
     );
   }
 
@@ -986,7 +1802,7 @@ mod tests {
         .span_by_grid(4, 2)
         .expect("span should be present since rasterization occured")
         .area_id(),
-      WALKABLE_AREA_ID as _
+      WALKABLE_AREA_ID as u32
     );
 
     heightfield.filter_walkable_low_height_spans(
@@ -1008,7 +1824,7 @@ mod tests {
         .span_by_grid(2, 2)
         .expect("span should be present since rasterization occured")
         .area_id(),
-      WALKABLE_AREA_ID as _
+      WALKABLE_AREA_ID as u32
     );
     // No ceiling here, so should still be walkable.
     assert_eq!(
@@ -1016,7 +1832,313 @@ mod tests {
         .span_by_grid(0, 2)
         .expect("span should be present since rasterization occured")
         .area_id(),
-      WALKABLE_AREA_ID as _
+      WALKABLE_AREA_ID as u32
+    );
+  }
+
+  #[test]
+  fn samples_solid_spans() {
+    let mut context = Context::new();
+
+    let min_bounds = Vec3::new(0.0, 0.0, 0.0);
+    let max_bounds = Vec3::new(4.0, 10.0, 2.0);
+
+    let mut heightfield =
+      Heightfield::new(&mut context, min_bounds, max_bounds, 1.0, 1.0)
+        .expect("creation succeeds");
+
+    let vertices = [
+      // Lower floor.
+      Vec3::new(0.0, 0.5, 0.0),
+      Vec3::new(2.0, 0.5, 0.0),
+      Vec3::new(2.0, 0.5, 2.0),
+      Vec3::new(0.0, 0.5, 2.0),
+      // Higher floor.
+      Vec3::new(2.0, 1.5, 0.0),
+      Vec3::new(4.0, 1.5, 0.0),
+      Vec3::new(4.0, 1.5, 2.0),
+      Vec3::new(2.0, 1.5, 2.0),
+      // Roof over the lower floor.
+      Vec3::new(0.0, 5.5, 0.0),
+      Vec3::new(2.0, 5.5, 0.0),
+      Vec3::new(2.0, 5.5, 2.0),
+      Vec3::new(0.0, 5.5, 2.0),
+    ];
+
+    let triangles = [
+      Vec3::new(0, 2, 1),
+      Vec3::new(2, 0, 3),
+      Vec3::new(4, 6, 5),
+      Vec3::new(6, 4, 7),
+      Vec3::new(8, 10, 9),
+      Vec3::new(10, 8, 11),
+    ];
+    let area_ids = [WALKABLE_AREA_ID; 6];
+
+    heightfield
+      .rasterize_indexed_triangles_i32(
+        &mut context,
+        &vertices,
+        &triangles,
+        &area_ids,
+        1,
+      )
+      .expect("rasterization succeeds");
+
+    assert_eq!(
+      heightfield.solid_spans_at(1.0, 1.0, SampleMode::Nearest),
+      [
+        SolidSpan {
+          min_height: 0.0,
+          max_height: 1.0,
+          clearance: 4.0,
+          area_id: WALKABLE_AREA_ID,
+        },
+        SolidSpan {
+          min_height: 5.0,
+          max_height: 6.0,
+          clearance: f32::INFINITY,
+          area_id: WALKABLE_AREA_ID,
+        },
+      ]
+    );
+
+    // Halfway between the cell centres of the two floors.
+    assert_eq!(
+      heightfield.solid_spans_at(
+        2.0,
+        1.0,
+        SampleMode::Bilinear { max_step: 1.0 }
+      ),
+      [SolidSpan {
+        min_height: 1.0,
+        max_height: 1.5,
+        clearance: f32::INFINITY,
+        area_id: WALKABLE_AREA_ID,
+      }]
+    );
+
+    // The roof is too far from the higher floor to be blended with it.
+    assert_eq!(
+      heightfield
+        .solid_spans_at(1.75, 1.0, SampleMode::Bilinear { max_step: 1.0 })
+        .iter()
+        .map(|span| span.max_height)
+        .collect::<Vec<_>>(),
+      [1.25, 6.0]
+    );
+
+    // The step is larger than `max_step`, so the floors are not blended.
+    assert_eq!(
+      heightfield
+        .solid_spans_at(2.0, 1.0, SampleMode::Bilinear { max_step: 0.5 })
+        .iter()
+        .map(|span| span.max_height)
+        .collect::<Vec<_>>(),
+      [2.0]
+    );
+
+    assert_eq!(heightfield.solid_spans_at(5.0, 1.0, SampleMode::Nearest), []);
+  }
+
+  #[test]
+  fn adds_and_removes_spans() {
+    let mut context = Context::new();
+    let mut heightfield = Heightfield::new(
+      &mut context,
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(5.0, 5.0, 5.0),
+      1.0,
+      0.5,
+    )
+    .expect("creating heightfield successful");
+
+    heightfield
+      .add_span(&mut context, 1, 2, 0..2, WALKABLE_AREA_ID, 1)
+      .expect("span added");
+    heightfield.add_span(&mut context, 1, 2, 5..9, 3, 1).expect("span added");
+    // Overlaps the first span, so it is merged.
+    heightfield.add_span(&mut context, 1, 2, 1..3, 4, 0).expect("span added");
+    assert_span_column_eq!(
+      HeightfieldSpan::collect(heightfield.span_by_grid(1, 2)),
+      [(0, 3, 4), (5, 9, 3)]
+    );
+    assert!(heightfield.span_by_grid(2, 1).is_none());
+
+    // Trims the first span and splits the second.
+    heightfield.remove_spans(&mut context, 1, 2, 2..7).expect("spans removed");
+    assert_span_column_eq!(
+      HeightfieldSpan::collect(heightfield.span_by_grid(1, 2)),
+      [(0, 2, 4), (7, 9, 3)]
+    );
+
+    // Removes the first span entirely and trims the bottom of the second.
+    heightfield.remove_spans(&mut context, 1, 2, 0..8).expect("spans removed");
+    assert_span_column_eq!(
+      HeightfieldSpan::collect(heightfield.span_by_grid(1, 2)),
+      [(8, 9, 3)]
+    );
+
+    heightfield.clear_column(1, 2);
+    assert!(heightfield.span_by_grid(1, 2).is_none());
+
+    // Freed spans are reused.
+    heightfield.add_span(&mut context, 4, 4, 1..2, 5, 1).expect("span added");
+    assert_span_column_eq!(
+      HeightfieldSpan::collect(heightfield.span_by_grid(4, 4)),
+      [(1, 2, 5)]
+    );
+
+    for (grid_x, grid_z) in [(-1, 0), (0, -1), (5, 0), (0, 5)] {
+      let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        let _ = heightfield.add_span(&mut context, grid_x, grid_z, 0..1, 1, 1);
+      }));
+      assert!(
+        result.is_err(),
+        "Expected out-of-bounds span to break an assert, but succeeded. x={}, z={}",
+        grid_x,
+        grid_z
+      );
+    }
+
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+      let _ = heightfield.add_span(&mut context, 1, 2, 3..3, 1, 1);
+    }));
+    assert!(
+      result.is_err(),
+      "Expected empty span to break an assert, but succeeded."
+    );
+  }
+
+  #[test]
+  fn visits_spans_mut() {
+    let mut context = Context::new();
+    let mut heightfield = Heightfield::new(
+      &mut context,
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(5.0, 5.0, 5.0),
+      1.0,
+      0.5,
+    )
+    .expect("creating heightfield successful");
+
+    // A floor with a thin beam above it along x = 2.
+    for grid_x in 0..5 {
+      for grid_z in 0..5 {
+        heightfield
+          .add_span(&mut context, grid_x, grid_z, 0..2, WALKABLE_AREA_ID, 1)
+          .expect("span added");
+      }
+    }
+    for grid_z in 0..5 {
+      heightfield
+        .add_span(&mut context, 2, grid_z, 6..7, WALKABLE_AREA_ID, 1)
+        .expect("span added");
+    }
+
+    // Mark spans unwalkable if there is no span at the same height on either
+    // side in the X direction.
+    heightfield.visit_spans_mut(|span| {
+      let has_side = |direction| {
+        span.neighbour_column(direction).iter().any(|neighbour| {
+          neighbour.height_max_u32() == span.span().height_max_u32()
+        })
+      };
+      if !has_side(Direction::NegX) && !has_side(Direction::PosX) {
+        span.set_area_id(INVALID_AREA_ID);
+      }
+    });
+
+    for (column_index, column_head) in heightfield.spans_iter().enumerate() {
+      if column_index % 5 == 2 {
+        assert_span_column_eq!(
+          HeightfieldSpan::collect(column_head),
+          [(0, 2, WALKABLE_AREA_ID as u32), (6, 7, INVALID_AREA_ID as u32)]
+        );
+      } else {
+        assert_span_column_eq!(
+          HeightfieldSpan::collect(column_head),
+          [(0, 2, WALKABLE_AREA_ID as u32)]
+        );
+      }
+    }
+
+    // Visitors see the original area IDs, so changes do not cascade.
+    heightfield.visit_spans_mut(|span| {
+      let neighbour_column = span.neighbour_column(Direction::NegX);
+      if neighbour_column
+        .first()
+        .is_some_and(|neighbour| neighbour.area_id() == WALKABLE_AREA_ID as u32)
+      {
+        span.set_area_id(1);
+      }
+    });
+    let floor_area_ids = heightfield
+      .spans_iter()
+      .map(|column_head| column_head.expect("floor exists").area_id())
+      .collect::<Vec<_>>();
+    for (column_index, area_id) in floor_area_ids.into_iter().enumerate() {
+      let expected_area_id =
+        if column_index % 5 == 0 { WALKABLE_AREA_ID as u32 } else { 1 };
+      assert_eq!(area_id, expected_area_id, "column {column_index}");
+    }
+  }
+
+  #[test]
+  fn rasterizes_point_cloud() {
+    let mut context = Context::new();
+
+    let points = [
+      Vec3::new(0.5, 1.0, 0.5),
+      Vec3::new(0.5, 1.2, 0.5),
+      Vec3::new(0.5, 3.0, 0.5),
+      Vec3::new(1.5, 2.0, 0.5),
+      // Outside the heightfield.
+      Vec3::new(-1.0, 2.0, 0.5),
+      // Not finite.
+      Vec3::new(0.5, f32::NAN, 0.5),
+      Vec3::new(f32::NAN, 2.0, 0.5),
+      Vec3::new(1.5, f32::INFINITY, 0.5),
+    ];
+    let area_ids = [1, 2, 3, 4, 5, 6, 7, 8];
+
+    let rasterize = |context: &mut Context, merge_distance: f32| {
+      let mut heightfield = Heightfield::new(
+        context,
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(2.0, 5.0, 1.0),
+        1.0,
+        0.5,
+      )
+      .expect("creating heightfield successful");
+      heightfield
+        .rasterize_point_cloud(
+          context,
+          &points,
+          &area_ids,
+          /* thickness= */ 0.5,
+          merge_distance,
+          1,
+        )
+        .expect("rasterization succeeds");
+      heightfield
+    };
+
+    let heightfield = rasterize(&mut context, 1.0);
+    // The first two points overlap, but the third is too far away to merge.
+    assert_span_column_eq!(
+      HeightfieldSpan::collect(heightfield.span_by_grid(0, 0)),
+      [(1, 3, 2), (5, 6, 3)]
+    );
+    assert_span_column_eq!(
+      HeightfieldSpan::collect(heightfield.span_by_grid(1, 0)),
+      [(3, 4, 4)]
+    );
+
+    let heightfield = rasterize(&mut context, 1.5);
+    assert_span_column_eq!(
+      HeightfieldSpan::collect(heightfield.span_by_grid(0, 0)),
+      [(1, 6, 3)]
     );
   }
 }
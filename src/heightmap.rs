@@ -0,0 +1,226 @@
+use crate::{shape::ColumnRect, Vec3, INVALID_AREA_ID};
+
+// A grid of 16-bit height samples describing terrain. See
+// `Heightfield::rasterize_heightmap`.
+#[derive(Debug, Clone, Copy)]
+pub struct Heightmap<'heightmap> {
+  // The number of samples along the X and Z axes.
+  pub samples_x: usize,
+  pub samples_z: usize,
+  // The height samples, where the sample at grid coordinates `x` and `z` is at
+  // index `x + z * samples_x`.
+  pub samples: &'heightmap [u16],
+  // The world height of one unit of a sample.
+  pub height_scale: f32,
+  // The world distance between neighbouring samples.
+  pub sample_spacing: f32,
+  // The world position of the first sample with a height of 0.
+  pub origin: Vec3<f32>,
+  // The area ID of each quad between four samples, where the quad whose first
+  // sample is at grid coordinates `x` and `z` is at index
+  // `x + z * (samples_x - 1)`.
+  pub area_ids: &'heightmap [u8],
+  // Optionally, whether each quad (indexed like `area_ids`) is a hole in the
+  // terrain. Holes are not rasterized.
+  pub holes: Option<&'heightmap [bool]>,
+}
+
+// A single quad of a Heightmap.
+pub(crate) struct HeightmapQuad {
+  min_x: f32,
+  min_z: f32,
+  size: f32,
+  // The world heights of the corners of the quad, in the order (min_x, min_z),
+  // (max_x, min_z), (min_x, max_z), (max_x, max_z).
+  heights: [f32; 4],
+  pub(crate) area_id: u8,
+}
+
+impl<'heightmap> Heightmap<'heightmap> {
+  fn sample_height(&self, x: usize, z: usize) -> f32 {
+    self.origin.y
+      + self.samples[x + z * self.samples_x] as f32 * self.height_scale
+  }
+
+  // Returns the quads of the heightmap that are not holes. Quads steeper than
+  // `walkable_slope_angle` (in degrees) at any corner are given
+  // `INVALID_AREA_ID`. Panics if the sizes of the slices do not match the
+  // number of samples.
+  pub(crate) fn quads(&self, walkable_slope_angle: f32) -> Vec<HeightmapQuad> {
+    assert_eq!(
+      self.samples.len(),
+      self.samples_x * self.samples_z,
+      "samples should have one entry per sample."
+    );
+    let quads_x = self.samples_x.saturating_sub(1);
+    let quads_z = self.samples_z.saturating_sub(1);
+    assert_eq!(
+      self.area_ids.len(),
+      quads_x * quads_z,
+      "area_ids should have one entry per quad."
+    );
+    if let Some(holes) = self.holes {
+      assert_eq!(
+        holes.len(),
+        quads_x * quads_z,
+        "holes should have one entry per quad."
+      );
+    }
+
+    let walkable_threshold = walkable_slope_angle.to_radians().cos();
+    let mut quads = Vec::with_capacity(quads_x * quads_z);
+    for z in 0..quads_z {
+      for x in 0..quads_x {
+        let quad_index = x + z * quads_x;
+        if self.holes.is_some_and(|holes| holes[quad_index]) {
+          continue;
+        }
+
+        let heights = [
+          self.sample_height(x, z),
+          self.sample_height(x + 1, z),
+          self.sample_height(x, z + 1),
+          self.sample_height(x + 1, z + 1),
+        ];
+
+        // The bilinear surface is steepest at one of its corners, where its
+        // gradient is given by the edges meeting at that corner.
+        let gradients_x = [heights[1] - heights[0], heights[3] - heights[2]];
+        let gradients_z = [heights[2] - heights[0], heights[3] - heights[1]];
+        let is_steep = gradients_x.iter().any(|gradient_x| {
+          gradients_z.iter().any(|gradient_z| {
+            let gradient_x = gradient_x / self.sample_spacing;
+            let gradient_z = gradient_z / self.sample_spacing;
+            let normal_y = 1.0
+              / (1.0 + gradient_x * gradient_x + gradient_z * gradient_z)
+                .sqrt();
+            normal_y < walkable_threshold
+          })
+        });
+
+        quads.push(HeightmapQuad {
+          min_x: self.origin.x + x as f32 * self.sample_spacing,
+          min_z: self.origin.z + z as f32 * self.sample_spacing,
+          size: self.sample_spacing,
+          heights,
+          area_id: if is_steep {
+            INVALID_AREA_ID
+          } else {
+            self.area_ids[quad_index]
+          },
+        });
+      }
+    }
+    quads
+  }
+}
+
+impl HeightmapQuad {
+  pub(crate) fn min_bounds(&self) -> Vec3<f32> {
+    Vec3::new(
+      self.min_x,
+      self.heights.iter().copied().fold(f32::INFINITY, f32::min),
+      self.min_z,
+    )
+  }
+
+  pub(crate) fn max_bounds(&self) -> Vec3<f32> {
+    Vec3::new(
+      self.min_x + self.size,
+      self.heights.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+      self.min_z + self.size,
+    )
+  }
+
+  // Returns the lowest and highest points of the bilinear surface of the quad
+  // within the column `rect`, or None if the quad does not overlap the column.
+  // A bilinear surface is linear along each axis, so its extremes within a
+  // rectangle are at the corners of the rectangle.
+  pub(crate) fn column_heights(&self, rect: &ColumnRect) -> Option<(f32, f32)> {
+    let min_u = ((rect.min_x - self.min_x) / self.size).max(0.0);
+    let max_u = ((rect.max_x - self.min_x) / self.size).min(1.0);
+    let min_v = ((rect.min_z - self.min_z) / self.size).max(0.0);
+    let max_v = ((rect.max_z - self.min_z) / self.size).min(1.0);
+    if min_u >= max_u || min_v >= max_v {
+      return None;
+    }
+
+    let height_at = |u: f32, v: f32| {
+      let [h_00, h_10, h_01, h_11] = self.heights;
+      let h_0 = h_00 + (h_10 - h_00) * u;
+      let h_1 = h_01 + (h_11 - h_01) * u;
+      h_0 + (h_1 - h_0) * v
+    };
+    let corner_heights = [
+      height_at(min_u, min_v),
+      height_at(max_u, min_v),
+      height_at(min_u, max_v),
+      height_at(max_u, max_v),
+    ];
+    Some((
+      corner_heights.iter().copied().fold(f32::INFINITY, f32::min),
+      corner_heights.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+    ))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    test_util::{collect_columns, new_heightfield},
+    Context, Heightmap, Vec3, INVALID_AREA_ID,
+  };
+
+  fn rasterize(
+    walkable_slope_angle: f32,
+    holes: Option<&[bool]>,
+  ) -> Vec<Vec<(u32, u32, u32)>> {
+    let mut context = Context::new();
+    let mut heightfield =
+      new_heightfield(&mut context, Vec3::new(4.0, 10.0, 2.0));
+
+    // A ramp up to a plateau.
+    let heightmap = Heightmap {
+      samples_x: 3,
+      samples_z: 2,
+      samples: &[0, 4, 4, 0, 4, 4],
+      height_scale: 0.5,
+      sample_spacing: 2.0,
+      origin: Vec3::new(0.0, 0.0, 0.0),
+      area_ids: &[1, 2],
+      holes,
+    };
+
+    heightfield
+      .rasterize_heightmap(&mut context, &heightmap, walkable_slope_angle, 1)
+      .expect("rasterization succeeds");
+
+    collect_columns(&heightfield)
+  }
+
+  #[test]
+  fn rasterizes_heightmap() {
+    let row = |ramp_area_id: u32| {
+      vec![
+        vec![(0, 2, ramp_area_id)],
+        vec![(2, 4, ramp_area_id)],
+        vec![(4, 5, 2)],
+        vec![(4, 5, 2)],
+      ]
+    };
+    assert_eq!(rasterize(50.0, None), [row(1), row(1)].concat());
+
+    // The ramp is at 45 degrees, so it is too steep.
+    let invalid_area_id = INVALID_AREA_ID as u32;
+    assert_eq!(
+      rasterize(40.0, None),
+      [row(invalid_area_id), row(invalid_area_id)].concat()
+    );
+
+    let row = vec![vec![(0, 2, 1)], vec![(2, 4, 1)], vec![], vec![]];
+    assert_eq!(
+      rasterize(50.0, Some(&[false, true])),
+      [row.clone(), row].concat()
+    );
+  }
+}
@@ -1,20 +1,54 @@
 mod vector;
 mod wrappers;
 
+mod area_registry;
+mod area_volume;
 mod compact_heightfield;
 mod contour_set;
+mod coordinate_convention;
+mod flow_field;
+mod grid_path;
 mod heightfield;
 mod heightfield_layer_set;
+mod heightmap;
+mod local_origin;
+mod nav_scene;
+#[cfg(feature = "obj")]
+mod obj;
 mod poly_mesh;
+mod region_info;
+mod sampling;
+mod shape;
+mod span_graph;
+#[cfg(test)]
+mod test_util;
+mod tri_mesh;
 pub mod util;
+mod vertex_input;
 
+pub use area_registry::{AreaInfo, AreaRegistry, AreaRegistryError};
+pub use area_volume::{AreaVolume, AreaVolumeShape};
 pub use compact_heightfield::{
-  CompactHeightfield, CompactHeightfieldState, HasRegions, NoRegions,
+  AreaFilterMode, AreaFilterOptions, CompactHeightfield,
+  CompactHeightfieldState, Direction, HasRegions, NoRegions, PartitionStrategy,
 };
-pub use contour_set::{ContourBuildFlags, ContourSet};
-pub use heightfield::{Heightfield, HeightfieldSpan};
+pub use contour_set::{Contour, ContourBuildFlags, ContourSet, ContourVertex};
+pub use coordinate_convention::{CoordinateConvention, Handedness, UpAxis};
+pub use flow_field::FlowField;
+pub use grid_path::GridPath;
+pub use heightfield::{Heightfield, HeightfieldSpan, HeightfieldSpanMut};
 pub use heightfield_layer_set::{HeightfieldLayer, HeightfieldLayerSet};
+pub use heightmap::Heightmap;
+pub use local_origin::LocalOrigin;
+pub use nav_scene::{NavScene, SceneInstance, SceneMesh};
+#[cfg(feature = "obj")]
+pub use obj::{ObjError, ObjMesh};
 pub use poly_mesh::{PolyMesh, PolyMeshDetail, NULL_INDEX};
+pub use region_info::{RegionInfo, RegionNeighbour};
+pub use sampling::{Floor, SampleMode, SolidSpan};
+pub use shape::AffineTransform;
+pub use tri_mesh::TriMesh;
+pub use vertex_input::{TriangleIndex, VertexPositions};
 
 pub use recastnavigation_sys::{
   RC_NULL_AREA as INVALID_AREA_ID, RC_WALKABLE_AREA as WALKABLE_AREA_ID,
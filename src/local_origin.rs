@@ -0,0 +1,371 @@
+use crate::{
+  nav_scene, Context, Heightfield, NavScene, PolyMesh, PolyMeshDetail, Vec3,
+};
+
+// A local origin for building one tile of a large world. Recast works in f32,
+// which loses precision far from the origin (e.g. at 100 km, f32 positions
+// are only precise to ~8 mm). Rebasing each tile so its positions are relative
+// to a nearby origin keeps the f32 positions small and precise, and outputs
+// are converted back to f64 world positions afterwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocalOrigin {
+  pub origin: Vec3<f64>,
+}
+
+impl LocalOrigin {
+  // Creates a local origin at `origin`. Using the minimum corner of a tile as
+  // the origin makes the tile's local minimum bounds exactly zero, so
+  // neighbouring tiles with sizes that are multiples of the cell size have
+  // aligned cells.
+  pub fn new(origin: Vec3<f64>) -> Self {
+    Self { origin }
+  }
+
+  // Converts a world position to the local f32 position.
+  pub fn to_local(&self, position: Vec3<f64>) -> Vec3<f32> {
+    Vec3::new(
+      (position.x - self.origin.x) as f32,
+      (position.y - self.origin.y) as f32,
+      (position.z - self.origin.z) as f32,
+    )
+  }
+
+  // Converts a local f32 position to the world position.
+  pub fn to_world(&self, position: Vec3<f32>) -> Vec3<f64> {
+    Vec3::new(
+      self.origin.x + position.x as f64,
+      self.origin.y + position.y as f64,
+      self.origin.z + position.z as f64,
+    )
+  }
+
+  // Converts world positions to local f32 positions.
+  pub fn vertices_to_local(&self, vertices: &[Vec3<f64>]) -> Vec<Vec3<f32>> {
+    vertices.iter().map(|&vertex| self.to_local(vertex)).collect()
+  }
+
+  // Creates a heightfield covering the world bounds in local coordinates. See
+  // `Heightfield::new`.
+  pub fn create_heightfield(
+    &self,
+    context: &mut Context,
+    min_bounds: Vec3<f64>,
+    max_bounds: Vec3<f64>,
+    cell_horizontal_size: f32,
+    cell_height: f32,
+  ) -> Result<Heightfield, ()> {
+    Heightfield::new(
+      context,
+      self.to_local(min_bounds),
+      self.to_local(max_bounds),
+      cell_horizontal_size,
+      cell_height,
+    )
+  }
+
+  // Rasterizes triangles with world positions into a heightfield created in
+  // local coordinates. See `Heightfield::rasterize_indexed_triangles_i32`.
+  pub fn rasterize_indexed_triangles(
+    &self,
+    heightfield: &mut Heightfield,
+    context: &mut Context,
+    vertices: &[Vec3<f64>],
+    triangles: &[Vec3<i32>],
+    area_ids: &[u8],
+    flag_merge_threshold: i32,
+  ) -> Result<(), ()> {
+    heightfield.rasterize_indexed_triangles_i32(
+      context,
+      &self.vertices_to_local(vertices),
+      triangles,
+      area_ids,
+      flag_merge_threshold,
+    )
+  }
+
+  // Rasterizes the included instances of `scene`, whose instance translations
+  // are world positions, into a heightfield created in local coordinates. The
+  // origin is in Recast's convention (like the heightfield's bounds), even if
+  // the scene uses another `coordinate_convention`. The translations are
+  // rebased in f64 before the instances are transformed, so only the precision
+  // of the f32 translations themselves is lost. See
+  // `Heightfield::rasterize_scene`.
+  pub fn rasterize_scene(
+    &self,
+    heightfield: &mut Heightfield,
+    context: &mut Context,
+    scene: &NavScene,
+    walkable_slope_angle: f32,
+    flag_merge_threshold: i32,
+  ) -> Result<(), ()> {
+    nav_scene::rasterize_scene(
+      heightfield,
+      context,
+      scene,
+      self,
+      walkable_slope_angle,
+      flag_merge_threshold,
+    )
+  }
+
+  // Returns the world positions of the vertices of `poly_mesh`, which must
+  // have been built in local coordinates. The positions are computed in f64
+  // from the grid coordinates of the vertices.
+  pub fn poly_mesh_vertices(&self, poly_mesh: &PolyMesh) -> Vec<Vec3<f64>> {
+    let min_bounds = poly_mesh.min_bounds();
+    let cell_horizontal_size = poly_mesh.cell_horizontal_size() as f64;
+    let cell_height = poly_mesh.cell_height() as f64;
+    poly_mesh
+      .vertices_iter()
+      .map(|vertex| {
+        let vertex = vertex.as_u16();
+        Vec3::new(
+          self.origin.x
+            + min_bounds.x as f64
+            + vertex.x as f64 * cell_horizontal_size,
+          self.origin.y + min_bounds.y as f64 + vertex.y as f64 * cell_height,
+          self.origin.z
+            + min_bounds.z as f64
+            + vertex.z as f64 * cell_horizontal_size,
+        )
+      })
+      .collect()
+  }
+
+  // Returns the world positions of the vertices of `poly_mesh_detail`, which
+  // must have been built in local coordinates.
+  pub fn poly_mesh_detail_vertices(
+    &self,
+    poly_mesh_detail: &PolyMeshDetail,
+  ) -> Vec<Vec3<f64>> {
+    poly_mesh_detail
+      .vertices()
+      .iter()
+      .map(|&vertex| self.to_world(vertex))
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    test_util::collect_columns, AffineTransform, CompactHeightfield, Context,
+    ContourBuildFlags, ContourSet, CoordinateConvention, Handedness,
+    LocalOrigin, NavScene, NoRegions, PolyMesh, PolyMeshDetail, SceneInstance,
+    SceneMesh, UpAxis, Vec3, WALKABLE_AREA_ID,
+  };
+
+  // Builds a nav mesh for a square at `offset`, returning the world positions
+  // of the PolyMesh and PolyMeshDetail vertices relative to `offset`.
+  fn build(offset: Vec3<f64>) -> (Vec<Vec3<f64>>, Vec<Vec3<f64>>) {
+    let mut context = Context::new();
+
+    let world = |x: f64, y: f64, z: f64| {
+      Vec3::new(offset.x + x, offset.y + y, offset.z + z)
+    };
+    let min_bounds = world(0.0, 0.0, 0.0);
+    let max_bounds = world(5.0, 5.0, 5.0);
+    let local_origin = LocalOrigin::new(min_bounds);
+
+    let mut heightfield = local_origin
+      .create_heightfield(&mut context, min_bounds, max_bounds, 0.5, 0.5)
+      .expect("creation succeeds");
+
+    let vertices = [
+      world(0.0, 0.5, 0.0),
+      world(5.0, 0.5, 0.0),
+      world(5.0, 1.5, 5.0),
+      world(0.0, 1.5, 5.0),
+    ];
+    local_origin
+      .rasterize_indexed_triangles(
+        &mut heightfield,
+        &mut context,
+        &vertices,
+        &[Vec3::new(0, 2, 1), Vec3::new(2, 0, 3)],
+        &[WALKABLE_AREA_ID, WALKABLE_AREA_ID],
+        1,
+      )
+      .expect("rasterization succeeds");
+
+    let compact_heightfield =
+      CompactHeightfield::<NoRegions>::new(&heightfield, &mut context, 3, 1)
+        .expect("creating CompactHeightfield succeeds")
+        .build_regions(&mut context, 0, 1, 1)
+        .expect("regions built");
+    let contour_set = ContourSet::new(
+      &compact_heightfield,
+      &mut context,
+      1.0,
+      10,
+      ContourBuildFlags {
+        tessellate_wall_edges: true,
+        tessellate_area_edges: false,
+      },
+    )
+    .expect("contours built");
+    let poly_mesh =
+      PolyMesh::new(&contour_set, &mut context, 6).expect("poly mesh built");
+    let poly_mesh_detail = PolyMeshDetail::new(
+      &poly_mesh,
+      &mut context,
+      &compact_heightfield,
+      1.0,
+      0.1,
+    )
+    .expect("poly mesh detail built");
+
+    let relative = |vertices: Vec<Vec3<f64>>| {
+      vertices
+        .into_iter()
+        .map(|vertex| {
+          Vec3::new(
+            vertex.x - offset.x,
+            vertex.y - offset.y,
+            vertex.z - offset.z,
+          )
+        })
+        .collect::<Vec<_>>()
+    };
+    (
+      relative(local_origin.poly_mesh_vertices(&poly_mesh)),
+      relative(local_origin.poly_mesh_detail_vertices(&poly_mesh_detail)),
+    )
+  }
+
+  #[test]
+  fn rebases_far_from_origin() {
+    let (poly_mesh_vertices, poly_mesh_detail_vertices) =
+      build(Vec3::new(0.0, 0.0, 0.0));
+    assert!(!poly_mesh_vertices.is_empty());
+    assert!(!poly_mesh_detail_vertices.is_empty());
+
+    // 150 km away, the results are identical relative to the tile.
+    assert_eq!(
+      build(Vec3::new(150_000.0, 2_000.0, -150_000.0)),
+      (poly_mesh_vertices, poly_mesh_detail_vertices)
+    );
+  }
+
+  #[test]
+  fn rasterizes_scene_far_from_origin() {
+    let mut scene = NavScene::new();
+    let quad = scene.add_mesh(SceneMesh::new(
+      vec![
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 1.0),
+        Vec3::new(0.0, 0.0, 1.0),
+      ],
+      vec![Vec3::new(0, 2, 1), Vec3::new(2, 0, 3)],
+      vec![WALKABLE_AREA_ID, WALKABLE_AREA_ID],
+    ));
+
+    let rasterize = |offset: Vec3<f64>| {
+      let mut context = Context::new();
+      let min_bounds = offset;
+      let max_bounds =
+        Vec3::new(offset.x + 5.0, offset.y + 5.0, offset.z + 5.0);
+      let local_origin = LocalOrigin::new(min_bounds);
+
+      let mut scene = scene.clone();
+      scene.instances = vec![SceneInstance::new(
+        quad,
+        AffineTransform::from_scale_translation(
+          Vec3::new(3.0, 1.0, 2.0),
+          Vec3::new(
+            (offset.x + 1.0) as f32,
+            (offset.y + 0.5) as f32,
+            (offset.z + 2.0) as f32,
+          ),
+        ),
+      )];
+
+      let mut heightfield = local_origin
+        .create_heightfield(&mut context, min_bounds, max_bounds, 1.0, 0.5)
+        .expect("creation succeeds");
+      local_origin
+        .rasterize_scene(&mut heightfield, &mut context, &scene, 45.0, 1)
+        .expect("rasterization succeeds");
+      collect_columns(&heightfield)
+    };
+
+    let columns = rasterize(Vec3::new(0.0, 0.0, 0.0));
+    assert_eq!(
+      columns.iter().filter(|column| !column.is_empty()).count(),
+      3 * 2
+    );
+    // 150 km away, the results are identical relative to the tile.
+    assert_eq!(rasterize(Vec3::new(150_000.0, 2_000.0, -150_000.0)), columns);
+  }
+
+  #[test]
+  fn rasterizes_z_up_scene_far_from_origin() {
+    let convention = CoordinateConvention {
+      up_axis: UpAxis::Z,
+      handedness: Handedness::Right,
+    };
+    let mut scene = NavScene::new();
+    scene.coordinate_convention = convention;
+    // A quad facing up along Z.
+    let quad = scene.add_mesh(SceneMesh::new(
+      vec![
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(1.0, 1.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+      ],
+      vec![Vec3::new(0, 1, 2), Vec3::new(0, 2, 3)],
+      vec![WALKABLE_AREA_ID, WALKABLE_AREA_ID],
+    ));
+
+    // `offset` is in the scene's (Z-up) convention.
+    let rasterize = |offset: Vec3<f64>| {
+      let mut context = Context::new();
+      let (min_bounds, max_bounds) = (
+        convention.to_recast(offset),
+        convention.to_recast(Vec3::new(
+          offset.x + 5.0,
+          offset.y - 5.0,
+          offset.z + 5.0,
+        )),
+      );
+      let local_origin = LocalOrigin::new(min_bounds);
+
+      let mut scene = scene.clone();
+      scene.instances = vec![SceneInstance::new(
+        quad,
+        AffineTransform::from_scale_translation(
+          Vec3::new(3.0, 1.0, 1.0),
+          Vec3::new(
+            (offset.x + 1.0) as f32,
+            (offset.y - 3.0) as f32,
+            (offset.z + 0.5) as f32,
+          ),
+        ),
+      )];
+
+      let mut heightfield = local_origin
+        .create_heightfield(&mut context, min_bounds, max_bounds, 1.0, 0.5)
+        .expect("creation succeeds");
+      local_origin
+        .rasterize_scene(&mut heightfield, &mut context, &scene, 45.0, 1)
+        .expect("rasterization succeeds");
+      collect_columns(&heightfield)
+    };
+
+    let columns = rasterize(Vec3::new(0.0, 0.0, 0.0));
+    // The quad covers X from 1 to 4 and Recast's Z (the negated Y) from 2 to 3.
+    for (index, column) in columns.iter().enumerate() {
+      let (x, z) = (index % 5, index / 5);
+      let expected_column = if (1..4).contains(&x) && z == 2 {
+        vec![(1, 2, WALKABLE_AREA_ID as u32)]
+      } else {
+        vec![]
+      };
+      assert_eq!(*column, expected_column, "x={x}, z={z}");
+    }
+    // 150 km away, the results are identical relative to the tile.
+    assert_eq!(rasterize(Vec3::new(150_000.0, -150_000.0, 2_000.0)), columns);
+  }
+}
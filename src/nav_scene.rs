@@ -0,0 +1,388 @@
+use crate::{
+  tri_mesh, util, AffineTransform, Context, CoordinateConvention, Heightfield,
+  LocalOrigin, Vec3, INVALID_AREA_ID,
+};
+
+// A triangle mesh that can be instanced many times in a NavScene.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneMesh {
+  vertices: Vec<Vec3<f32>>,
+  triangles: Vec<Vec3<i32>>,
+  area_ids: Vec<u8>,
+}
+
+impl SceneMesh {
+  // Creates a mesh from `triangles`, which contains i32 Vec3's where x,y,z
+  // corresponds to indices of `vertices`. `area_ids` must hold one ID per
+  // triangle. Panics if any triangle indices are out of range.
+  pub fn new(
+    vertices: Vec<Vec3<f32>>,
+    triangles: Vec<Vec3<i32>>,
+    area_ids: Vec<u8>,
+  ) -> Self {
+    assert_eq!(
+      triangles.len(),
+      area_ids.len(),
+      "area_ids should have one entry per triangle."
+    );
+    tri_mesh::assert_triangle_indices(&vertices, &triangles);
+    Self { vertices, triangles, area_ids }
+  }
+
+  pub fn vertices(&self) -> &[Vec3<f32>] {
+    &self.vertices
+  }
+
+  pub fn triangles(&self) -> &[Vec3<i32>] {
+    &self.triangles
+  }
+
+  pub fn area_ids(&self) -> &[u8] {
+    &self.area_ids
+  }
+}
+
+// A placement of a SceneMesh in a NavScene.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneInstance {
+  // The index of the mesh in the scene (as returned by `NavScene::add_mesh`).
+  pub mesh: usize,
+  pub transform: AffineTransform,
+  // If set, replaces the area IDs of every triangle of the mesh.
+  pub area_override: Option<u8>,
+  // If None, triangles steeper than the walkable slope angle are unwalkable.
+  // If set, all triangles are walkable (keeping their area IDs) or unwalkable
+  // regardless of their slope.
+  pub walkable_override: Option<bool>,
+  // Whether the instance is rasterized at all.
+  pub included: bool,
+}
+
+impl SceneInstance {
+  // Creates an included instance of `mesh` without any overrides.
+  pub fn new(mesh: usize, transform: AffineTransform) -> Self {
+    Self {
+      mesh,
+      transform,
+      area_override: None,
+      walkable_override: None,
+      included: true,
+    }
+  }
+}
+
+// A scene made of shared meshes and instances of them. This avoids
+// duplicating the vertices of meshes that are used many times.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NavScene {
+  meshes: Vec<SceneMesh>,
+  pub instances: Vec<SceneInstance>,
+  // The convention of the meshes and instance transforms. Transformed
+  // vertices are converted to Recast's convention when rasterizing.
+  pub coordinate_convention: CoordinateConvention,
+}
+
+impl NavScene {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  // Adds `mesh` to the scene and returns the index to use in SceneInstances.
+  pub fn add_mesh(&mut self, mesh: SceneMesh) -> usize {
+    self.meshes.push(mesh);
+    self.meshes.len() - 1
+  }
+
+  pub fn meshes(&self) -> &[SceneMesh] {
+    &self.meshes
+  }
+
+  // Returns the bounds of all included instances in Recast's convention (e.g.
+  // to create a heightfield) as `(min_bounds, max_bounds)`, or None if there
+  // are no included vertices. Panics if an instance refers to a mesh that is
+  // not in the scene.
+  pub fn bounds(&self) -> Option<(Vec3<f32>, Vec3<f32>)> {
+    let mut bounds: Option<(Vec3<f32>, Vec3<f32>)> = None;
+    for instance in self.instances.iter().filter(|instance| instance.included) {
+      for &vertex in self.meshes[instance.mesh].vertices() {
+        let vertex = instance.transform.transform_point(vertex);
+        bounds = Some(match bounds {
+          None => (vertex, vertex),
+          Some((min_bounds, max_bounds)) => (
+            Vec3::new(
+              min_bounds.x.min(vertex.x),
+              min_bounds.y.min(vertex.y),
+              min_bounds.z.min(vertex.z),
+            ),
+            Vec3::new(
+              max_bounds.x.max(vertex.x),
+              max_bounds.y.max(vertex.y),
+              max_bounds.z.max(vertex.z),
+            ),
+          ),
+        });
+      }
+    }
+    bounds.map(|(min_bounds, max_bounds)| {
+      self.coordinate_convention.bounds_to_recast(min_bounds, max_bounds)
+    })
+  }
+}
+
+pub(crate) fn rasterize_scene(
+  heightfield: &mut Heightfield,
+  context: &mut Context,
+  scene: &NavScene,
+  local_origin: &LocalOrigin,
+  walkable_slope_angle: f32,
+  flag_merge_threshold: i32,
+) -> Result<(), ()> {
+  // Buffers reused between instances, so only one instance is transformed at a
+  // time.
+  let mut vertices = Vec::new();
+  let mut flipped_triangles = Vec::new();
+  let mut area_ids = Vec::new();
+
+  for instance in scene.instances.iter().filter(|instance| instance.included) {
+    let mesh = &scene.meshes[instance.mesh];
+
+    // The translation is rebased in f64 before the rest of the transform is
+    // applied, so the transformed vertices are small and precise. The local
+    // origin is in Recast's convention, so the translation is converted to
+    // Recast's convention to be rebased, and converted back (which is exact) to
+    // be applied before the vertices are converted.
+    let coordinate_convention = scene.coordinate_convention;
+    let translation = instance.transform.translation;
+    let transform = AffineTransform {
+      translation: coordinate_convention.from_recast(local_origin.to_local(
+        coordinate_convention.to_recast(Vec3::new(
+          translation.x as f64,
+          translation.y as f64,
+          translation.z as f64,
+        )),
+      )),
+      ..instance.transform
+    };
+    vertices.clear();
+    vertices.extend(mesh.vertices().iter().map(|&vertex| {
+      coordinate_convention.to_recast(transform.transform_point(vertex))
+    }));
+
+    // Mirroring flips the winding of triangles, which would flip their normals
+    // when checking their slope. Mirroring twice (by the transform and the
+    // coordinate convention) keeps the winding.
+    let triangles = if instance.transform.is_mirroring()
+      != coordinate_convention.is_mirroring()
+    {
+      flipped_triangles.clear();
+      flipped_triangles.extend(
+        mesh
+          .triangles()
+          .iter()
+          .map(|triangle| Vec3::new(triangle.x, triangle.z, triangle.y)),
+      );
+      flipped_triangles.as_slice()
+    } else {
+      mesh.triangles()
+    };
+
+    area_ids.clear();
+    match instance.area_override {
+      None => area_ids.extend_from_slice(mesh.area_ids()),
+      Some(area_id) => area_ids.resize(triangles.len(), area_id),
+    }
+    match instance.walkable_override {
+      // SAFETY: SceneMesh checks that all its triangle indices are in range,
+      // and `vertices` has one entry per vertex of the mesh.
+      None => unsafe {
+        util::clear_unwalkable_triangles_unchecked(
+          context,
+          walkable_slope_angle,
+          &vertices,
+          triangles,
+          &mut area_ids,
+        )
+      },
+      Some(true) => {}
+      Some(false) => area_ids.fill(INVALID_AREA_ID),
+    }
+
+    // SAFETY: SceneMesh checks that all its triangle indices are in range, and
+    // `vertices` has one entry per vertex of the mesh.
+    unsafe {
+      heightfield.rasterize_indexed_triangles_i32_unchecked(
+        context,
+        &vertices,
+        triangles,
+        &area_ids,
+        flag_merge_threshold,
+      )
+    }?;
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    AffineTransform, Context, CoordinateConvention, Handedness, Heightfield,
+    NavScene, SceneInstance, SceneMesh, UpAxis, Vec3, INVALID_AREA_ID,
+    WALKABLE_AREA_ID,
+  };
+
+  #[test]
+  fn rasterizes_instances() {
+    let mut scene = NavScene::new();
+    let quad = scene.add_mesh(SceneMesh::new(
+      vec![
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 1.0),
+        Vec3::new(0.0, 0.0, 1.0),
+      ],
+      vec![Vec3::new(0, 2, 1), Vec3::new(2, 0, 3)],
+      vec![WALKABLE_AREA_ID, WALKABLE_AREA_ID],
+    ));
+
+    let translated = |x: f32, z: f32| {
+      AffineTransform::from_scale_translation(
+        Vec3::new(1.0, 1.0, 1.0),
+        Vec3::new(x, 0.5, z),
+      )
+    };
+    let steep = |x: f32, z: f32| AffineTransform {
+      x_axis: Vec3::new(1.0, 3.0, 0.0),
+      ..translated(x, z)
+    };
+
+    scene.instances = vec![
+      SceneInstance::new(
+        quad,
+        AffineTransform::from_scale_translation(
+          Vec3::new(2.0, 1.0, 2.0),
+          Vec3::new(0.0, 0.5, 0.0),
+        ),
+      ),
+      // Mirrored, so it covers X from 3 to 4.
+      SceneInstance::new(
+        quad,
+        AffineTransform {
+          x_axis: Vec3::new(-1.0, 0.0, 0.0),
+          ..translated(4.0, 0.0)
+        },
+      ),
+      SceneInstance {
+        area_override: Some(5),
+        ..SceneInstance::new(quad, translated(6.0, 0.0))
+      },
+      SceneInstance::new(quad, steep(0.0, 4.0)),
+      SceneInstance {
+        walkable_override: Some(true),
+        ..SceneInstance::new(quad, steep(3.0, 4.0))
+      },
+      SceneInstance {
+        walkable_override: Some(false),
+        ..SceneInstance::new(quad, translated(6.0, 4.0))
+      },
+      SceneInstance {
+        included: false,
+        ..SceneInstance::new(quad, translated(3.0, 6.0))
+      },
+    ];
+
+    assert_eq!(
+      scene.bounds(),
+      Some((Vec3::new(0.0, 0.5, 0.0), Vec3::new(7.0, 3.5, 5.0)))
+    );
+
+    let mut context = Context::new();
+    let mut heightfield = Heightfield::new(
+      &mut context,
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(8.0, 5.0, 8.0),
+      1.0,
+      0.5,
+    )
+    .expect("creating heightfield successful");
+    heightfield
+      .rasterize_scene(&mut context, &scene, 45.0, 1)
+      .expect("rasterization succeeds");
+
+    let area_at = |grid_x: i32, grid_z: i32| {
+      heightfield.span_by_grid(grid_x, grid_z).map(|span| span.area_id() as u8)
+    };
+    assert_eq!(area_at(0, 0), Some(WALKABLE_AREA_ID));
+    assert_eq!(area_at(1, 1), Some(WALKABLE_AREA_ID));
+    assert_eq!(area_at(3, 0), Some(WALKABLE_AREA_ID));
+    assert_eq!(area_at(6, 0), Some(5));
+    assert_eq!(area_at(0, 4), Some(INVALID_AREA_ID));
+    assert_eq!(area_at(3, 4), Some(WALKABLE_AREA_ID));
+    assert_eq!(area_at(6, 4), Some(INVALID_AREA_ID));
+    assert_eq!(area_at(3, 6), None);
+    assert_eq!(area_at(2, 3), None);
+  }
+
+  #[test]
+  fn rasterizes_z_up_left_handed_scene() {
+    let mut scene = NavScene::new();
+    scene.coordinate_convention =
+      CoordinateConvention { up_axis: UpAxis::Z, handedness: Handedness::Left };
+    // A quad facing up along Z.
+    let quad = scene.add_mesh(SceneMesh::new(
+      vec![
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(1.0, 1.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+      ],
+      vec![Vec3::new(0, 1, 2), Vec3::new(0, 2, 3)],
+      vec![WALKABLE_AREA_ID, WALKABLE_AREA_ID],
+    ));
+    scene.instances = vec![
+      SceneInstance::new(
+        quad,
+        AffineTransform::from_scale_translation(
+          Vec3::new(1.0, 1.0, 1.0),
+          Vec3::new(2.0, 2.0, 0.5),
+        ),
+      ),
+      // Mirrored, so it covers X from 4 to 5.
+      SceneInstance::new(
+        quad,
+        AffineTransform::from_scale_translation(
+          Vec3::new(-1.0, 1.0, 1.0),
+          Vec3::new(5.0, 2.0, 0.5),
+        ),
+      ),
+    ];
+
+    // Recast's Y axis is the Z axis, and its Z axis is the Y axis.
+    assert_eq!(
+      scene.bounds(),
+      Some((Vec3::new(2.0, 0.5, 2.0), Vec3::new(5.0, 0.5, 3.0)))
+    );
+
+    let mut context = Context::new();
+    let mut heightfield = Heightfield::new(
+      &mut context,
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(5.0, 5.0, 5.0),
+      1.0,
+      0.5,
+    )
+    .expect("creating heightfield successful");
+    heightfield
+      .rasterize_scene(&mut context, &scene, 45.0, 1)
+      .expect("rasterization succeeds");
+
+    let area_at = |grid_x: i32, grid_z: i32| {
+      heightfield.span_by_grid(grid_x, grid_z).map(|span| span.area_id() as u8)
+    };
+    // Both quads still face up despite the convention mirroring them, so they
+    // are walkable.
+    assert_eq!(area_at(2, 2), Some(WALKABLE_AREA_ID));
+    assert_eq!(area_at(4, 2), Some(WALKABLE_AREA_ID));
+    assert_eq!(area_at(3, 2), None);
+    assert_eq!(area_at(2, 1), None);
+  }
+}
@@ -0,0 +1,283 @@
+use std::{fmt, path::Path};
+
+use crate::Vec3;
+
+// Geometry loaded from a Wavefront OBJ file. Only vertex positions, faces and
+// material groups are loaded. The triangles can be passed directly to
+// `Heightfield::rasterize_indexed_triangles_i32` or `TriMesh::new`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjMesh {
+  pub vertices: Vec<Vec3<f32>>,
+  // Triangles indexing into `vertices`. Faces with more than 3 vertices are
+  // triangulated as a fan around their first vertex.
+  pub triangles: Vec<Vec3<i32>>,
+  // The material of each triangle as an index into `materials`, or None if the
+  // face came before any `usemtl`. These can be used as the materials of
+  // `util::classify_triangles`.
+  pub triangle_materials: Vec<Option<usize>>,
+  // The names of the materials used by `usemtl`, in order of first use.
+  pub materials: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum ObjError {
+  Io(std::io::Error),
+  // A line could not be parsed. `line_number` starts at 1.
+  Parse { line_number: usize, message: String },
+}
+
+impl fmt::Display for ObjError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ObjError::Io(error) => write!(f, "Failed to read OBJ file: {}", error),
+      ObjError::Parse { line_number, message } => {
+        write!(f, "Failed to parse OBJ line {}: {}", line_number, message)
+      }
+    }
+  }
+}
+
+impl std::error::Error for ObjError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      ObjError::Io(error) => Some(error),
+      ObjError::Parse { .. } => None,
+    }
+  }
+}
+
+impl From<std::io::Error> for ObjError {
+  fn from(error: std::io::Error) -> Self {
+    ObjError::Io(error)
+  }
+}
+
+impl ObjMesh {
+  // Loads the OBJ file at `path`. See `ObjMesh::parse`.
+  pub fn load(path: impl AsRef<Path>) -> Result<ObjMesh, ObjError> {
+    Self::parse(&std::fs::read_to_string(path)?)
+  }
+
+  // Parses the contents of an OBJ file. Face indices can be positive (starting
+  // at 1) or negative (relative to the last vertex defined so far), and may
+  // include texture coordinate and normal indices, which are ignored.
+  // Unsupported statements (e.g. normals, groups, `mtllib`) are ignored.
+  pub fn parse(source: &str) -> Result<ObjMesh, ObjError> {
+    let mut mesh = ObjMesh {
+      vertices: Vec::new(),
+      triangles: Vec::new(),
+      triangle_materials: Vec::new(),
+      materials: Vec::new(),
+    };
+    let mut material = None;
+    let mut face = Vec::new();
+
+    for (line_index, line) in source.lines().enumerate() {
+      let parse_error = |message: String| ObjError::Parse {
+        line_number: line_index + 1,
+        message,
+      };
+
+      let line = line.split('#').next().unwrap_or_default();
+      let mut tokens = line.split_whitespace();
+      match tokens.next() {
+        Some("v") => {
+          let mut coordinates = [0.0; 3];
+          for coordinate in coordinates.iter_mut() {
+            let token = tokens.next().ok_or_else(|| {
+              parse_error("Vertex has fewer than 3 coordinates.".into())
+            })?;
+            *coordinate = token
+              .parse::<f32>()
+              .ok()
+              .filter(|value| value.is_finite())
+              .ok_or_else(|| {
+                parse_error(format!("Invalid vertex coordinate: {}", token))
+              })?;
+          }
+          mesh.vertices.push(Vec3::new(
+            coordinates[0],
+            coordinates[1],
+            coordinates[2],
+          ));
+        }
+        Some("f") => {
+          face.clear();
+          for token in tokens {
+            let index = token
+              .split('/')
+              .next()
+              .and_then(|index| index.parse::<i64>().ok())
+              .ok_or_else(|| {
+                parse_error(format!("Invalid face index: {}", token))
+              })?;
+            let vertices_len = mesh.vertices.len() as i64;
+            let index = match index {
+              index if index > 0 => index - 1,
+              index if index < 0 => vertices_len + index,
+              _ => -1,
+            };
+            if index < 0 || index >= vertices_len {
+              return Err(parse_error(format!(
+                "Face index out-of-bounds vertex: {}, vertices_len={}",
+                token, vertices_len
+              )));
+            }
+            face.push(index as i32);
+          }
+          if face.len() < 3 {
+            return Err(parse_error("Face has fewer than 3 vertices.".into()));
+          }
+
+          for i in 1..(face.len() - 1) {
+            mesh.triangles.push(Vec3::new(face[0], face[i], face[i + 1]));
+            mesh.triangle_materials.push(material);
+          }
+        }
+        Some("usemtl") => {
+          let name = tokens
+            .next()
+            .ok_or_else(|| parse_error("Missing material name.".into()))?;
+          material = Some(
+            match mesh.materials.iter().position(|material| material == name) {
+              Some(index) => index,
+              None => {
+                mesh.materials.push(name.into());
+                mesh.materials.len() - 1
+              }
+            },
+          );
+        }
+        _ => {}
+      }
+    }
+
+    Ok(mesh)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    obj::ObjError, Context, Heightfield, ObjMesh, TriMesh, Vec3,
+    WALKABLE_AREA_ID,
+  };
+
+  const SOURCE: &str = "\
+# A floor and a ramp.
+mtllib level.mtl
+o Level
+v 0 0 0
+v 5 0 0
+v 5 0 5
+v 0 0 5 # Trailing comment.
+vn 0 1 0
+vt 0 0
+
+f 1/1/1 3/1/1 2/1/1
+usemtl ground
+f 3//1 1//1 4//1
+usemtl ramp
+v 10 2.5 0
+v 10 2.5 5
+f -5 -1 -2 -4
+usemtl ground
+f 1 3 2
+";
+
+  #[test]
+  fn parses_obj() {
+    let mesh = ObjMesh::parse(SOURCE).expect("parsing succeeds");
+
+    assert_eq!(mesh.vertices.len(), 6);
+    assert_eq!(mesh.vertices[3], Vec3::new(0.0, 0.0, 5.0));
+    assert_eq!(mesh.vertices[5], Vec3::new(10.0, 2.5, 5.0));
+    assert_eq!(
+      mesh.triangles,
+      [
+        Vec3::new(0, 2, 1),
+        Vec3::new(2, 0, 3),
+        // The quad is triangulated as a fan.
+        Vec3::new(1, 5, 4),
+        Vec3::new(1, 4, 2),
+        Vec3::new(0, 2, 1),
+      ]
+    );
+    assert_eq!(mesh.materials, ["ground", "ramp"]);
+    assert_eq!(
+      mesh.triangle_materials,
+      [None, Some(0), Some(1), Some(1), Some(0)]
+    );
+
+    let tri_mesh = TriMesh::new(mesh.vertices.clone(), mesh.triangles.clone());
+    let mut context = Context::new();
+    let mut heightfield = Heightfield::new(
+      &mut context,
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(10.0, 5.0, 5.0),
+      1.0,
+      0.5,
+    )
+    .expect("creating heightfield successful");
+    heightfield
+      .rasterize_tri_mesh(
+        &mut context,
+        &tri_mesh,
+        &vec![WALKABLE_AREA_ID; mesh.triangles.len()],
+        1,
+      )
+      .expect("rasterization succeeds");
+    assert!(heightfield.span_by_grid(2, 2).is_some());
+  }
+
+  #[test]
+  fn loads_obj_file() {
+    let path = std::env::temp_dir()
+      .join(format!("recast_rs_loads_obj_file_{}.obj", std::process::id()));
+    std::fs::write(&path, SOURCE).expect("writing succeeds");
+    let mesh = ObjMesh::load(&path);
+    std::fs::remove_file(&path).expect("removing succeeds");
+
+    assert_eq!(
+      mesh.expect("loading succeeds"),
+      ObjMesh::parse(SOURCE).expect("parsing succeeds")
+    );
+    let error = ObjMesh::load(&path).expect_err("loading fails");
+    assert!(matches!(error, ObjError::Io(_)));
+    assert!(std::error::Error::source(&error).is_some());
+  }
+
+  #[test]
+  fn rejects_invalid_obj() {
+    let vertices = "v 0 0 0\nv 1 0 0\nv 1 0 1\n";
+    for (source, expected_line_number) in [
+      ("v 0 0\n", 1),
+      ("v 0 nan 0\n", 1),
+      ("v 0 x 0\n", 1),
+      (&format!("{}f 1 2\n", vertices) as &str, 4),
+      (&format!("{}f 1 2 4\n", vertices), 4),
+      (&format!("{}f 1 2 0\n", vertices), 4),
+      (&format!("{}f 1 2 -4\n", vertices), 4),
+      (&format!("{}f 1 2 a\n", vertices), 4),
+      (&format!("{}usemtl\n", vertices), 4),
+    ] {
+      match ObjMesh::parse(source) {
+        Err(error @ ObjError::Parse { line_number, .. }) => {
+          assert_eq!(
+            line_number, expected_line_number,
+            "Incorrect line for source: {:?}",
+            source
+          );
+          assert!(error.to_string().starts_with(&format!(
+            "Failed to parse OBJ line {}: ",
+            line_number
+          )));
+        }
+        result => panic!(
+          "Expected parse error for source: {:?}, got {:?}",
+          source, result
+        ),
+      }
+    }
+  }
+}
@@ -88,6 +88,24 @@ impl PolyMesh {
       .map(|index| PolyMeshPolygon { poly_mesh: self, index })
   }
 
+  // Sets the user-defined flags of the polygon at `index` (e.g. Detour's
+  // polygon flags, which filter path queries).
+  pub fn set_polygon_flags(&mut self, index: usize, flags: u16) {
+    assert!(
+      index < self.polygons_len(),
+      "Polygon index out-of-bounds. index={}, len={}",
+      index,
+      self.polygons_len()
+    );
+    // SAFETY: `flags` has a length of `maxpolys` which is >= `npolys`.
+    // Therefore, the slice is fully covered by the allocated portion of
+    // `flags`. `self` is borrowed mutably, so the slice is exclusive.
+    let polygon_flags = unsafe {
+      std::slice::from_raw_parts_mut(self.poly_mesh.flags, self.polygons_len())
+    };
+    polygon_flags[index] = flags;
+  }
+
   pub fn max_vertices_per_polygon(&self) -> i32 {
     self.poly_mesh.nvp
   }
@@ -0,0 +1,202 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use recastnavigation_sys::RC_BORDER_REG;
+
+use crate::{
+  compact_heightfield::Direction, CompactHeightfield, HasRegions, Vec3,
+};
+
+// Statistics about a single region in a CompactHeightfield. See
+// `CompactHeightfield::region_infos`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionInfo {
+  pub region_id: u16,
+  // The number of spans in the region.
+  pub span_count: usize,
+  // The area IDs of the spans in the region, in ascending order.
+  pub area_ids: Vec<u8>,
+  // The world bounds of the cells in the region. The Y components are the
+  // lowest and highest floor heights in the region.
+  pub min_bounds: Vec3<f32>,
+  pub max_bounds: Vec3<f32>,
+  // The regions that this region touches, in ascending order of region ID.
+  pub neighbours: Vec<RegionNeighbour>,
+}
+
+// An edge in the region adjacency graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionNeighbour {
+  pub region_id: u16,
+  // The cells of this region that are connected to a span in the neighbouring
+  // region, in ascending order. See `CompactHeightfield::cell` for how cells
+  // are indexed.
+  pub cells: Vec<usize>,
+}
+
+pub(crate) fn build_region_infos(
+  compact_heightfield: &CompactHeightfield<HasRegions>,
+) -> Vec<RegionInfo> {
+  let is_region =
+    |region_id: u16| region_id != 0 && region_id & RC_BORDER_REG == 0;
+
+  let region_ids = compact_heightfield
+    .spans_iter()
+    .map(|span| span.region_id())
+    .collect::<Vec<_>>();
+  let floors = compact_heightfield
+    .spans_iter()
+    .map(|span| span.y_start_f32())
+    .collect::<Vec<_>>();
+  let areas = compact_heightfield.span_areas();
+
+  let cell_size = compact_heightfield.cell_horizontal_size();
+  let heightfield_min_bounds = compact_heightfield.min_bounds();
+
+  let mut region_infos = BTreeMap::new();
+  let mut region_area_ids = BTreeMap::<u16, BTreeSet<u8>>::new();
+  let mut region_neighbours =
+    BTreeMap::<u16, BTreeMap<u16, BTreeSet<usize>>>::new();
+
+  for (cell_index, cell) in compact_heightfield.cells_iter().enumerate() {
+    let x = cell_index as i32 % compact_heightfield.grid_width();
+    let z = cell_index as i32 / compact_heightfield.grid_width();
+    let cell_min = Vec3::new(
+      heightfield_min_bounds.x + x as f32 * cell_size,
+      0.0,
+      heightfield_min_bounds.z + z as f32 * cell_size,
+    );
+    let cell_max =
+      Vec3::new(cell_min.x + cell_size, 0.0, cell_min.z + cell_size);
+
+    for span_index in cell {
+      let region_id = region_ids[span_index];
+      if !is_region(region_id) {
+        continue;
+      }
+
+      let floor = floors[span_index];
+      let region_info = region_infos.entry(region_id).or_insert(RegionInfo {
+        region_id,
+        span_count: 0,
+        area_ids: Vec::new(),
+        min_bounds: Vec3::new(cell_min.x, floor, cell_min.z),
+        max_bounds: Vec3::new(cell_max.x, floor, cell_max.z),
+        neighbours: Vec::new(),
+      });
+      region_info.span_count += 1;
+      region_info.min_bounds.x = region_info.min_bounds.x.min(cell_min.x);
+      region_info.min_bounds.y = region_info.min_bounds.y.min(floor);
+      region_info.min_bounds.z = region_info.min_bounds.z.min(cell_min.z);
+      region_info.max_bounds.x = region_info.max_bounds.x.max(cell_max.x);
+      region_info.max_bounds.y = region_info.max_bounds.y.max(floor);
+      region_info.max_bounds.z = region_info.max_bounds.z.max(cell_max.z);
+
+      region_area_ids.entry(region_id).or_default().insert(areas[span_index]);
+
+      for direction in
+        [Direction::NegX, Direction::PosZ, Direction::PosX, Direction::NegZ]
+      {
+        let Some(neighbour_index) =
+          compact_heightfield.neighbour_span_index(x, z, span_index, direction)
+        else {
+          continue;
+        };
+
+        let neighbour_region_id = region_ids[neighbour_index];
+        if neighbour_region_id == region_id || !is_region(neighbour_region_id) {
+          continue;
+        }
+
+        region_neighbours
+          .entry(region_id)
+          .or_default()
+          .entry(neighbour_region_id)
+          .or_default()
+          .insert(cell_index);
+      }
+    }
+  }
+
+  region_infos
+    .into_values()
+    .map(|mut region_info| {
+      region_info.area_ids = region_area_ids
+        .remove(&region_info.region_id)
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+      region_info.neighbours = region_neighbours
+        .remove(&region_info.region_id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(region_id, cells)| RegionNeighbour {
+          region_id,
+          cells: cells.into_iter().collect(),
+        })
+        .collect();
+      region_info
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    test_util::floor_compact_heightfield, Context, RegionInfo, RegionNeighbour,
+    Vec3, WALKABLE_AREA_ID,
+  };
+
+  #[test]
+  fn builds_region_infos() {
+    let mut context = Context::new();
+
+    let mut compact_heightfield =
+      floor_compact_heightfield(&mut context, Vec3::new(6.0, 10.0, 3.0));
+
+    // Split the heightfield into two areas so that it forms two regions.
+    compact_heightfield.mark_box_area_with_id(
+      &mut context,
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(2.99, 1.0, 3.0),
+      1,
+    );
+
+    let compact_heightfield = compact_heightfield
+      .build_regions(
+        &mut context,
+        /* border_size= */ 0,
+        /* min_region_area= */ 1,
+        /* merge_region_area= */ 1,
+      )
+      .expect("building regions succeeds");
+
+    let region_infos = compact_heightfield.region_infos();
+    assert_eq!(
+      region_infos,
+      [
+        RegionInfo {
+          region_id: 1,
+          span_count: 9,
+          area_ids: vec![1],
+          min_bounds: Vec3::new(0.0, 1.0, 0.0),
+          max_bounds: Vec3::new(3.0, 1.0, 3.0),
+          neighbours: vec![RegionNeighbour {
+            region_id: 2,
+            cells: vec![2, 8, 14],
+          }],
+        },
+        RegionInfo {
+          region_id: 2,
+          span_count: 9,
+          area_ids: vec![WALKABLE_AREA_ID],
+          min_bounds: Vec3::new(3.0, 1.0, 0.0),
+          max_bounds: Vec3::new(6.0, 1.0, 3.0),
+          neighbours: vec![RegionNeighbour {
+            region_id: 1,
+            cells: vec![3, 9, 15],
+          }],
+        },
+      ]
+    );
+  }
+}
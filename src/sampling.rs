@@ -0,0 +1,113 @@
+use crate::Vec3;
+
+// How to determine heights at positions between cell centres.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SampleMode {
+  // Use the height of the cell containing the position.
+  Nearest,
+  // Interpolate between the heights of the four cell centres surrounding the
+  // position. A neighbouring cell only contributes if it has a height within
+  // `max_step` of the height in the cell containing the position (otherwise the
+  // containing cell's height is used in its place). This prevents blending
+  // with unrelated floors, e.g. across a ledge or with a floor above.
+  Bilinear { max_step: f32 },
+}
+
+// A walkable floor in a CompactHeightfield. See
+// `CompactHeightfield::floors_at`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Floor {
+  // The index of the span this floor is the bottom of.
+  pub span_index: usize,
+  // The world height of the floor.
+  pub height: f32,
+  // The open space above the floor in world units. Recast stores this as at
+  // most 255 cells, so larger clearances are capped.
+  pub clearance: f32,
+  pub area_id: u8,
+}
+
+// A solid span in a Heightfield. See `Heightfield::solid_spans_at`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SolidSpan {
+  // The world height of the bottom of the span.
+  pub min_height: f32,
+  // The world height of the top of the span (i.e. the floor on top of it).
+  pub max_height: f32,
+  // The open space between the top of the span and the next span up in world
+  // units. This is infinite for the highest span in a column.
+  pub clearance: f32,
+  pub area_id: u8,
+}
+
+// Returns the grid coordinates of the cell containing `x` and `z`, or None if
+// the position is outside the grid.
+pub(crate) fn containing_cell(
+  min_bounds: Vec3<f32>,
+  cell_size: f32,
+  grid_width: i32,
+  grid_height: i32,
+  x: f32,
+  z: f32,
+) -> Option<(i32, i32)> {
+  let cell_x = ((x - min_bounds.x) / cell_size).floor();
+  let cell_z = ((z - min_bounds.z) / cell_size).floor();
+  if cell_x < 0.0
+    || cell_z < 0.0
+    || cell_x >= grid_width as f32
+    || cell_z >= grid_height as f32
+  {
+    return None;
+  }
+  Some((cell_x as i32, cell_z as i32))
+}
+
+// Determines the height at `x` and `z` for the surface at `height` (the height
+// in the cell containing the position) according to `sample_mode`.
+// `column_heights` returns the heights of all surfaces in the column at the
+// provided grid coordinates (or nothing if the coordinates are outside the
+// grid).
+pub(crate) fn sample_height<Heights: IntoIterator<Item = f32>>(
+  sample_mode: SampleMode,
+  min_bounds: Vec3<f32>,
+  cell_size: f32,
+  x: f32,
+  z: f32,
+  height: f32,
+  column_heights: impl Fn(i32, i32) -> Heights,
+) -> f32 {
+  let max_step = match sample_mode {
+    SampleMode::Nearest => return height,
+    SampleMode::Bilinear { max_step } => max_step,
+  };
+
+  // Shift by half a cell so that integer coordinates are at cell centres.
+  let grid_x = (x - min_bounds.x) / cell_size - 0.5;
+  let grid_z = (z - min_bounds.z) / cell_size - 0.5;
+  let corner_x = grid_x.floor();
+  let corner_z = grid_z.floor();
+  let t_x = grid_x - corner_x;
+  let t_z = grid_z - corner_z;
+
+  let corner_height = |delta_x: i32, delta_z: i32| {
+    let mut closest_height = height;
+    let mut closest_difference = f32::INFINITY;
+    for column_height in
+      column_heights(corner_x as i32 + delta_x, corner_z as i32 + delta_z)
+    {
+      let difference = (column_height - height).abs();
+      if difference <= max_step && difference < closest_difference {
+        closest_height = column_height;
+        closest_difference = difference;
+      }
+    }
+    closest_height
+  };
+
+  let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+  lerp(
+    lerp(corner_height(0, 0), corner_height(1, 0), t_x),
+    lerp(corner_height(0, 1), corner_height(1, 1), t_x),
+    t_z,
+  )
+}
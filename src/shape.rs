@@ -0,0 +1,453 @@
+use crate::Vec3;
+
+// The number of iterations used when searching along the axis of a capsule.
+// Each iteration shrinks the search range to 2/3 of its size.
+const SEARCH_ITERATIONS: usize = 64;
+
+// An affine transform used to place shapes and scene instances in the world.
+// Besides rotating and translating, it can also scale, shear and mirror. Points
+// are transformed to `x_axis * x + y_axis * y + z_axis * z + translation`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct AffineTransform {
+  pub x_axis: Vec3<f32>,
+  pub y_axis: Vec3<f32>,
+  pub z_axis: Vec3<f32>,
+  pub translation: Vec3<f32>,
+}
+
+impl AffineTransform {
+  pub const IDENTITY: Self = Self {
+    x_axis: Vec3 { x: 1.0, y: 0.0, z: 0.0 },
+    y_axis: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+    z_axis: Vec3 { x: 0.0, y: 0.0, z: 1.0 },
+    translation: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+  };
+
+  pub fn from_translation(translation: Vec3<f32>) -> Self {
+    Self { translation, ..Self::IDENTITY }
+  }
+
+  // Creates a transform rotating by `angle` radians around `axis` (which must
+  // be normalized), followed by `translation`.
+  pub fn from_axis_angle(
+    translation: Vec3<f32>,
+    axis: Vec3<f32>,
+    angle: f32,
+  ) -> Self {
+    let (sin, cos) = (angle * 0.5).sin_cos();
+    let [q_x, q_y, q_z, q_w] = [axis.x * sin, axis.y * sin, axis.z * sin, cos];
+    // Rotates `point` by the unit quaternion `q`:
+    // v' = v + 2w(q x v) + 2q x (q x v)
+    let rotate = |point: Vec3<f32>| {
+      let t_x = 2.0 * (q_y * point.z - q_z * point.y);
+      let t_y = 2.0 * (q_z * point.x - q_x * point.z);
+      let t_z = 2.0 * (q_x * point.y - q_y * point.x);
+      Vec3::new(
+        point.x + q_w * t_x + (q_y * t_z - q_z * t_y),
+        point.y + q_w * t_y + (q_z * t_x - q_x * t_z),
+        point.z + q_w * t_z + (q_x * t_y - q_y * t_x),
+      )
+    };
+    Self {
+      x_axis: rotate(Vec3::new(1.0, 0.0, 0.0)),
+      y_axis: rotate(Vec3::new(0.0, 1.0, 0.0)),
+      z_axis: rotate(Vec3::new(0.0, 0.0, 1.0)),
+      translation,
+    }
+  }
+
+  // Creates a transform scaling by `scale` along each axis, followed by
+  // `translation`.
+  pub fn from_scale_translation(
+    scale: Vec3<f32>,
+    translation: Vec3<f32>,
+  ) -> Self {
+    Self {
+      x_axis: Vec3::new(scale.x, 0.0, 0.0),
+      y_axis: Vec3::new(0.0, scale.y, 0.0),
+      z_axis: Vec3::new(0.0, 0.0, scale.z),
+      translation,
+    }
+  }
+
+  pub fn transform_point(&self, point: Vec3<f32>) -> Vec3<f32> {
+    let axes = [self.x_axis, self.y_axis, self.z_axis];
+    let coordinates = [point.x, point.y, point.z];
+    let mut result = self.translation;
+    for (axis, coordinate) in axes.iter().zip(coordinates) {
+      result.x += axis.x * coordinate;
+      result.y += axis.y * coordinate;
+      result.z += axis.z * coordinate;
+    }
+    result
+  }
+
+  // Returns whether the transform mirrors shapes (i.e. has a negative
+  // determinant), which flips the winding of triangles.
+  pub fn is_mirroring(&self) -> bool {
+    let (a, b, c) = (self.x_axis, self.y_axis, self.z_axis);
+    let determinant = a.x * (b.y * c.z - b.z * c.y)
+      - b.x * (a.y * c.z - a.z * c.y)
+      + c.x * (a.y * b.z - a.z * b.y);
+    determinant < 0.0
+  }
+}
+
+impl Default for AffineTransform {
+  fn default() -> Self {
+    Self::IDENTITY
+  }
+}
+
+// The XZ bounds of a heightfield column.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ColumnRect {
+  pub(crate) min_x: f32,
+  pub(crate) min_z: f32,
+  pub(crate) max_x: f32,
+  pub(crate) max_z: f32,
+}
+
+impl ColumnRect {
+  // Returns the horizontal distance from `x` and `z` to the rect.
+  fn distance(&self, x: f32, z: f32) -> f32 {
+    let delta_x = (self.min_x - x).max(x - self.max_x).max(0.0);
+    let delta_z = (self.min_z - z).max(z - self.max_z).max(0.0);
+    (delta_x * delta_x + delta_z * delta_z).sqrt()
+  }
+}
+
+// Returns the corners and surface triangles of a box with `half_extents`.
+pub(crate) fn box_triangles(
+  half_extents: Vec3<f32>,
+) -> ([Vec3<f32>; 8], [Vec3<usize>; 12]) {
+  let Vec3 { x, y, z } = half_extents;
+  let vertices = [
+    Vec3::new(-x, -y, -z),
+    Vec3::new(x, -y, -z),
+    Vec3::new(x, -y, z),
+    Vec3::new(-x, -y, z),
+    Vec3::new(-x, y, -z),
+    Vec3::new(x, y, -z),
+    Vec3::new(x, y, z),
+    Vec3::new(-x, y, z),
+  ];
+  let triangles = [
+    Vec3::new(0, 1, 2),
+    Vec3::new(0, 2, 3),
+    Vec3::new(4, 6, 5),
+    Vec3::new(4, 7, 6),
+    Vec3::new(0, 4, 5),
+    Vec3::new(0, 5, 1),
+    Vec3::new(1, 5, 6),
+    Vec3::new(1, 6, 2),
+    Vec3::new(2, 6, 7),
+    Vec3::new(2, 7, 3),
+    Vec3::new(3, 7, 4),
+    Vec3::new(3, 4, 0),
+  ];
+  (vertices, triangles)
+}
+
+// Returns the lowest and highest points of the convex solid bounded by
+// `triangles` within the column `rect`, or None if the solid does not overlap
+// the column. The extremes of the solid within the column are always at a
+// vertex of a surface triangle clipped to the column.
+pub(crate) fn convex_hull_column_heights(
+  triangles: &[[Vec3<f32>; 3]],
+  rect: &ColumnRect,
+) -> Option<(f32, f32)> {
+  let mut heights: Option<(f32, f32)> = None;
+  for triangle in triangles {
+    let mut polygon = triangle.to_vec();
+    polygon = clip_polygon(&polygon, |v| v.x - rect.min_x);
+    polygon = clip_polygon(&polygon, |v| rect.max_x - v.x);
+    polygon = clip_polygon(&polygon, |v| v.z - rect.min_z);
+    polygon = clip_polygon(&polygon, |v| rect.max_z - v.z);
+    for vertex in polygon {
+      heights = Some(match heights {
+        None => (vertex.y, vertex.y),
+        Some((low, high)) => (low.min(vertex.y), high.max(vertex.y)),
+      });
+    }
+  }
+  heights
+}
+
+// Clips `polygon` to the half-space where `side` is non-negative.
+fn clip_polygon(
+  polygon: &[Vec3<f32>],
+  side: impl Fn(&Vec3<f32>) -> f32,
+) -> Vec<Vec3<f32>> {
+  let mut clipped = Vec::with_capacity(polygon.len() + 1);
+  for (index, vertex) in polygon.iter().enumerate() {
+    let next_vertex = &polygon[(index + 1) % polygon.len()];
+    let (side_a, side_b) = (side(vertex), side(next_vertex));
+    if side_a >= 0.0 {
+      clipped.push(*vertex);
+    }
+    if (side_a >= 0.0) != (side_b >= 0.0) {
+      let t = side_a / (side_a - side_b);
+      clipped.push(lerp(*vertex, *next_vertex, t));
+    }
+  }
+  clipped
+}
+
+// Returns the lowest and highest points of the sphere within the column
+// `rect`, or None if the sphere does not overlap the column.
+pub(crate) fn sphere_column_heights(
+  center: Vec3<f32>,
+  radius: f32,
+  rect: &ColumnRect,
+) -> Option<(f32, f32)> {
+  let distance = rect.distance(center.x, center.z);
+  if distance > radius {
+    return None;
+  }
+  let half_height = (radius * radius - distance * distance).sqrt();
+  Some((center.y - half_height, center.y + half_height))
+}
+
+// Returns the lowest and highest points of the capsule around the segment from
+// `start` to `end` within the column `rect`, or None if the capsule does not
+// overlap the column. The capsule is the union of spheres along the segment,
+// so this searches along the segment for the extreme spheres. The distance from
+// the segment to the column is convex along the segment, which makes the
+// heights of the sphere tops concave (and bottoms convex) along the segment.
+pub(crate) fn capsule_column_heights(
+  start: Vec3<f32>,
+  end: Vec3<f32>,
+  radius: f32,
+  rect: &ColumnRect,
+) -> Option<(f32, f32)> {
+  let distance = |t: f32| {
+    let point = lerp(start, end, t);
+    rect.distance(point.x, point.z)
+  };
+
+  let closest_t = ternary_search(0.0, 1.0, |t| -distance(t));
+  if distance(closest_t) > radius {
+    return None;
+  }
+
+  // The range of the segment whose spheres overlap the column.
+  let start_t = bisect(0.0, closest_t, |t| distance(t) <= radius);
+  let end_t = bisect(1.0, closest_t, |t| distance(t) <= radius);
+
+  let sphere_half_height = |t: f32| {
+    let distance = distance(t).min(radius);
+    (radius * radius - distance * distance).sqrt()
+  };
+  let center_height = |t: f32| start.y + (end.y - start.y) * t;
+
+  let top = |t: f32| center_height(t) + sphere_half_height(t);
+  let bottom = |t: f32| center_height(t) - sphere_half_height(t);
+  let top_t = ternary_search(start_t, end_t, top);
+  let bottom_t = ternary_search(start_t, end_t, |t| -bottom(t));
+  Some((bottom(bottom_t), top(top_t)))
+}
+
+// Returns where the concave function `f` is maximized between `low` and
+// `high`.
+fn ternary_search(mut low: f32, mut high: f32, f: impl Fn(f32) -> f32) -> f32 {
+  for _ in 0..SEARCH_ITERATIONS {
+    let third = (high - low) / 3.0;
+    if f(low + third) < f(high - third) {
+      low += third;
+    } else {
+      high -= third;
+    }
+  }
+  (low + high) * 0.5
+}
+
+// Returns the point furthest from `inside` towards `outside` where `predicate`
+// is true, assuming `predicate(inside)` is true and `predicate` changes at most
+// once between the two.
+fn bisect(
+  mut outside: f32,
+  mut inside: f32,
+  predicate: impl Fn(f32) -> bool,
+) -> f32 {
+  if predicate(outside) {
+    return outside;
+  }
+  for _ in 0..SEARCH_ITERATIONS {
+    let middle = (outside + inside) * 0.5;
+    if predicate(middle) {
+      inside = middle;
+    } else {
+      outside = middle;
+    }
+  }
+  inside
+}
+
+fn lerp(a: Vec3<f32>, b: Vec3<f32>, t: f32) -> Vec3<f32> {
+  Vec3::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t, a.z + (b.z - a.z) * t)
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    test_util::{collect_columns, new_heightfield},
+    AffineTransform, Context, Vec3,
+  };
+
+  #[test]
+  fn rasterizes_box_like_triangles() {
+    let mut context = Context::new();
+
+    let transform = AffineTransform::from_axis_angle(
+      Vec3::new(5.1, 3.3, 4.8),
+      Vec3::new(0.0, 0.6, 0.8),
+      0.7,
+    );
+    let half_extents = Vec3::new(2.2, 1.3, 1.7);
+
+    let mut heightfield =
+      new_heightfield(&mut context, Vec3::new(10.0, 10.0, 10.0));
+    heightfield
+      .rasterize_box(&mut context, half_extents, &transform, 5, 1)
+      .expect("rasterization succeeds");
+
+    // Rasterizing the surface of the box gives the same tops and bottoms, but
+    // does not fill the inside of the box.
+    let (vertices, triangles) = super::box_triangles(half_extents);
+    let triangle_vertices = triangles
+      .iter()
+      .flat_map(|triangle| [triangle.x, triangle.y, triangle.z])
+      .map(|index| transform.transform_point(vertices[index]))
+      .collect::<Vec<_>>();
+    let mut surface_heightfield =
+      new_heightfield(&mut context, Vec3::new(10.0, 10.0, 10.0));
+    surface_heightfield
+      .rasterize_triangles(&mut context, &triangle_vertices, &[5; 12], 1)
+      .expect("rasterization succeeds");
+
+    let columns = collect_columns(&heightfield);
+    let surface_columns = collect_columns(&surface_heightfield);
+    assert!(columns.iter().any(|column| !column.is_empty()));
+    for (column, surface_column) in columns.iter().zip(&surface_columns) {
+      let expected_column =
+        match (surface_column.first(), surface_column.last()) {
+          (Some(bottom), Some(top)) => vec![(bottom.0, top.1, 5)],
+          _ => vec![],
+        };
+      assert_eq!(*column, expected_column);
+    }
+  }
+
+  #[test]
+  fn rasterizes_sphere_and_capsule() {
+    let mut context = Context::new();
+
+    let transform = AffineTransform::from_translation(Vec3::new(5.5, 4.2, 5.5));
+
+    let mut heightfield =
+      new_heightfield(&mut context, Vec3::new(10.0, 10.0, 10.0));
+    heightfield
+      .rasterize_sphere(&mut context, 2.6, &transform, 3, 1)
+      .expect("rasterization succeeds");
+
+    let columns = collect_columns(&heightfield);
+    // The column containing the centre spans the whole sphere.
+    assert_eq!(columns[5 + 5 * 10], [(3, 14, 3)]);
+    // The nearest point of this column is 1.5 units away, so the sphere is
+    // sqrt(2.6^2 - 1.5^2) ~= 2.12 high on each side.
+    assert_eq!(columns[5 + 7 * 10], [(4, 13, 3)]);
+    // These columns are more than 2.6 units away.
+    assert_eq!(columns[8 + 8 * 10], []);
+    assert_eq!(columns[5 + 9 * 10], []);
+
+    // A capsule without length is a sphere.
+    let mut capsule_heightfield =
+      new_heightfield(&mut context, Vec3::new(10.0, 10.0, 10.0));
+    capsule_heightfield
+      .rasterize_capsule(&mut context, 0.0, 2.6, &transform, 3, 1)
+      .expect("rasterization succeeds");
+    assert_eq!(collect_columns(&capsule_heightfield), columns);
+
+    // A capsule lying along the X axis.
+    let transform = AffineTransform::from_axis_angle(
+      Vec3::new(5.5, 4.2, 5.5),
+      Vec3::new(0.0, 0.0, 1.0),
+      std::f32::consts::FRAC_PI_2,
+    );
+    let mut capsule_heightfield =
+      new_heightfield(&mut context, Vec3::new(10.0, 10.0, 10.0));
+    capsule_heightfield
+      .rasterize_capsule(&mut context, 2.0, 1.1, &transform, 3, 1)
+      .expect("rasterization succeeds");
+    let capsule_columns = collect_columns(&capsule_heightfield);
+    for x in 0..10 {
+      let expected_column = match x {
+        // The cylinder part of the capsule.
+        3..=7 => vec![(6, 11, 3)],
+        // The end caps: the nearest point of these columns is 0.5 units from
+        // the end of the segment, so the capsule is ~0.98 high on each side.
+        2 | 8 => vec![(6, 11, 3)],
+        _ => vec![],
+      };
+      assert_eq!(capsule_columns[x + 5 * 10], expected_column, "x={x}");
+    }
+  }
+
+  #[test]
+  fn rasterizes_convex_hull() {
+    let mut context = Context::new();
+
+    // A square pyramid with its apex 3 units above the centre of its base.
+    let vertices = [
+      Vec3::new(-2.0, 0.0, -2.0),
+      Vec3::new(2.0, 0.0, -2.0),
+      Vec3::new(2.0, 0.0, 2.0),
+      Vec3::new(-2.0, 0.0, 2.0),
+      Vec3::new(0.0, 3.0, 0.0),
+    ];
+    let triangles = [
+      Vec3::new(0, 1, 2),
+      Vec3::new(0, 2, 3),
+      Vec3::new(0, 4, 1),
+      Vec3::new(1, 4, 2),
+      Vec3::new(2, 4, 3),
+      Vec3::new(3, 4, 0),
+    ];
+    let transform = AffineTransform::from_translation(Vec3::new(5.5, 1.0, 5.5));
+
+    let mut heightfield =
+      new_heightfield(&mut context, Vec3::new(10.0, 10.0, 10.0));
+    heightfield
+      .rasterize_convex_hull(
+        &mut context,
+        &vertices,
+        &triangles,
+        &transform,
+        4,
+        1,
+      )
+      .expect("rasterization succeeds");
+
+    // Each column is filled from the base to the highest point of the pyramid
+    // within the column, which is nearest to the apex.
+    let columns = collect_columns(&heightfield);
+    for z in 0..10 {
+      for x in 0..10 {
+        let distance = (x as i32 - 5).abs().max((z as i32 - 5).abs());
+        let expected_column = match distance {
+          // The apex is at a height of 4.
+          0 => vec![(2, 8, 4)],
+          // The nearest point is 0.5 units from the apex, so it is at a height
+          // of 1 + 3 * (1 - 0.5 / 2) = 3.25.
+          1 => vec![(2, 7, 4)],
+          // The nearest point is 1.5 units from the apex, so it is at a height
+          // of 1 + 3 * (1 - 1.5 / 2) = 1.75.
+          2 => vec![(2, 4, 4)],
+          _ => vec![],
+        };
+        assert_eq!(columns[x + z * 10], expected_column, "x={x}, z={z}");
+      }
+    }
+  }
+}
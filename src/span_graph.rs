@@ -0,0 +1,169 @@
+use std::cmp::Ordering;
+
+use crate::{
+  compact_heightfield::Direction, CompactHeightfield, CompactHeightfieldState,
+  SampleMode, Vec3,
+};
+
+const DIRECTIONS: [Direction; 4] =
+  [Direction::NegX, Direction::PosZ, Direction::PosX, Direction::NegZ];
+
+// The spans of a CompactHeightfield viewed as a graph, where spans are
+// connected to the spans they can be walked to (including diagonally).
+pub(crate) struct SpanGraph<'compact_heightfield, TypeState>
+where
+  TypeState: CompactHeightfieldState,
+{
+  pub(crate) compact_heightfield:
+    &'compact_heightfield CompactHeightfield<TypeState>,
+  // The grid coordinates of the cell containing each span.
+  span_cells: Vec<(i32, i32)>,
+}
+
+impl<'compact_heightfield, TypeState> SpanGraph<'compact_heightfield, TypeState>
+where
+  TypeState: CompactHeightfieldState,
+{
+  pub(crate) fn new(
+    compact_heightfield: &'compact_heightfield CompactHeightfield<TypeState>,
+  ) -> Self {
+    let mut span_cells = vec![(0, 0); compact_heightfield.spans_len()];
+    for (cell_index, cell) in compact_heightfield.cells_iter().enumerate() {
+      let x = cell_index as i32 % compact_heightfield.grid_width();
+      let z = cell_index as i32 / compact_heightfield.grid_width();
+      for span_index in cell {
+        span_cells[span_index] = (x, z);
+      }
+    }
+
+    Self { compact_heightfield, span_cells }
+  }
+
+  // Returns the grid coordinates of the cell containing `span_index`.
+  pub(crate) fn span_cell(&self, span_index: usize) -> (i32, i32) {
+    self.span_cells[span_index]
+  }
+
+  // Returns the world position of the floor of `span_index` at the centre of
+  // its cell.
+  pub(crate) fn position(&self, span_index: usize) -> Vec3<f32> {
+    let min_bounds = self.compact_heightfield.min_bounds();
+    let cell_horizontal_size = self.compact_heightfield.cell_horizontal_size();
+    let (x, z) = self.span_cell(span_index);
+    Vec3::new(
+      min_bounds.x + (x as f32 + 0.5) * cell_horizontal_size,
+      self.compact_heightfield.span(span_index).y_start_f32(),
+      min_bounds.z + (z as f32 + 0.5) * cell_horizontal_size,
+    )
+  }
+
+  // Returns the span connected to `span_index` in `direction` if it is
+  // `passable` and within the walkable climb.
+  fn step(
+    &self,
+    span_index: usize,
+    direction: Direction,
+    passable: &impl Fn(usize) -> bool,
+  ) -> Option<usize> {
+    let (x, z) = self.span_cell(span_index);
+    let neighbour_index = self
+      .compact_heightfield
+      .neighbour_span_index(x, z, span_index, direction)?;
+    if !passable(neighbour_index) {
+      return None;
+    }
+
+    let climb = self.compact_heightfield.span(span_index).y_start_u16() as i32
+      - self.compact_heightfield.span(neighbour_index).y_start_u16() as i32;
+    if climb.abs() > self.compact_heightfield.walkable_climb() {
+      return None;
+    }
+    Some(neighbour_index)
+  }
+
+  // Returns the `passable` spans that can be walked to from `span_index`.
+  // Diagonal moves are only allowed if both orthogonal routes to the diagonal
+  // span are passable, so moves never cut corners.
+  pub(crate) fn neighbours(
+    &self,
+    span_index: usize,
+    passable: impl Fn(usize) -> bool,
+  ) -> Vec<usize> {
+    let mut neighbours = Vec::with_capacity(8);
+    for (direction_index, &direction) in DIRECTIONS.iter().enumerate() {
+      let Some(neighbour_index) = self.step(span_index, direction, &passable)
+      else {
+        continue;
+      };
+      neighbours.push(neighbour_index);
+
+      let next_direction = DIRECTIONS[(direction_index + 1) % 4];
+      let diagonal_index =
+        self.step(neighbour_index, next_direction, &passable);
+      let other_diagonal_index = self
+        .step(span_index, next_direction, &passable)
+        .and_then(|other_index| self.step(other_index, direction, &passable));
+      if let Some(diagonal_index) = diagonal_index {
+        if Some(diagonal_index) == other_diagonal_index {
+          neighbours.push(diagonal_index);
+        }
+      }
+    }
+    neighbours
+  }
+}
+
+// Returns the distance between `from` and `to`.
+pub(crate) fn distance(from: Vec3<f32>, to: Vec3<f32>) -> f32 {
+  let delta_x = to.x - from.x;
+  let delta_y = to.y - from.y;
+  let delta_z = to.z - from.z;
+  (delta_x * delta_x + delta_y * delta_y + delta_z * delta_z).sqrt()
+}
+
+// Returns the `passable` floor at the cell containing `position` that is
+// closest to `position` vertically.
+pub(crate) fn closest_floor<TypeState: CompactHeightfieldState>(
+  compact_heightfield: &CompactHeightfield<TypeState>,
+  position: Vec3<f32>,
+  passable: impl Fn(usize) -> bool,
+) -> Option<usize> {
+  compact_heightfield
+    .floors_at(position.x, position.z, SampleMode::Nearest)
+    .into_iter()
+    .filter(|floor| passable(floor.span_index))
+    .min_by(|a, b| {
+      (a.height - position.y).abs().total_cmp(&(b.height - position.y).abs())
+    })
+    .map(|floor| floor.span_index)
+}
+
+// An entry in the open list of a search. Ordered so that the lowest cost is the
+// greatest (since BinaryHeap is a max-heap).
+pub(crate) struct OpenNode {
+  pub(crate) cost: f32,
+  pub(crate) span_index: usize,
+}
+
+impl PartialEq for OpenNode {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for OpenNode {}
+
+impl PartialOrd for OpenNode {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for OpenNode {
+  fn cmp(&self, other: &Self) -> Ordering {
+    other
+      .cost
+      .total_cmp(&self.cost)
+      .then_with(|| other.span_index.cmp(&self.span_index))
+  }
+}
@@ -0,0 +1,91 @@
+// Fixtures shared by the tests of several modules.
+
+use crate::{
+  CompactHeightfield, Context, Heightfield, HeightfieldSpan, NoRegions, Vec3,
+  WALKABLE_AREA_ID,
+};
+
+// Creates an empty heightfield from the origin to `max_bounds` with 1x1 cells
+// that are 0.5 high.
+pub(crate) fn new_heightfield(
+  context: &mut Context,
+  max_bounds: Vec3<f32>,
+) -> Heightfield {
+  Heightfield::new(context, Vec3::new(0.0, 0.0, 0.0), max_bounds, 1.0, 0.5)
+    .expect("creating heightfield successful")
+}
+
+// Returns the (min height, max height, area ID) of the spans in each column of
+// `heightfield`.
+pub(crate) fn collect_columns(
+  heightfield: &Heightfield,
+) -> Vec<Vec<(u32, u32, u32)>> {
+  heightfield
+    .spans_iter()
+    .map(|column_head| {
+      HeightfieldSpan::collect(column_head)
+        .iter()
+        .map(|span| {
+          (span.height_min_u32(), span.height_max_u32(), span.area_id())
+        })
+        .collect()
+    })
+    .collect()
+}
+
+// Rasterizes a walkable floor into `heightfield`, spanning the XZ rectangle
+// from `min_corner` to `max_corner`. Both corners must be at the same height.
+pub(crate) fn rasterize_floor(
+  context: &mut Context,
+  heightfield: &mut Heightfield,
+  min_corner: Vec3<f32>,
+  max_corner: Vec3<f32>,
+) {
+  assert_eq!(min_corner.y, max_corner.y, "The floor must be horizontal.");
+
+  let vertices = [
+    min_corner,
+    Vec3::new(max_corner.x, min_corner.y, min_corner.z),
+    max_corner,
+    Vec3::new(min_corner.x, min_corner.y, max_corner.z),
+  ];
+
+  let triangles = [Vec3::new(0, 2, 1), Vec3::new(2, 0, 3)];
+  let area_ids = [WALKABLE_AREA_ID, WALKABLE_AREA_ID];
+
+  heightfield
+    .rasterize_indexed_triangles_i32(
+      context, &vertices, &triangles, &area_ids, 1,
+    )
+    .expect("rasterization succeeds");
+}
+
+// Creates a compact heightfield from the origin to `max_bounds` with 1x1x1
+// cells, and a walkable floor at a height of 0.5 covering the whole grid.
+pub(crate) fn floor_compact_heightfield(
+  context: &mut Context,
+  max_bounds: Vec3<f32>,
+) -> CompactHeightfield<NoRegions> {
+  let mut heightfield =
+    Heightfield::new(context, Vec3::new(0.0, 0.0, 0.0), max_bounds, 1.0, 1.0)
+      .expect("creation succeeds");
+
+  rasterize_floor(
+    context,
+    &mut heightfield,
+    Vec3::new(0.0, 0.5, 0.0),
+    Vec3::new(max_bounds.x, 0.5, max_bounds.z),
+  );
+
+  CompactHeightfield::<NoRegions>::new(&heightfield, context, 3, 0)
+    .expect("creating CompactHeightfield succeeds")
+}
+
+// Creates a compact heightfield of a walkable `size` x `size` square floor
+// with 1x1 cells.
+pub(crate) fn flat_compact_heightfield(
+  context: &mut Context,
+  size: f32,
+) -> CompactHeightfield<NoRegions> {
+  floor_compact_heightfield(context, Vec3::new(size, size, size))
+}
@@ -0,0 +1,193 @@
+use crate::Vec3;
+
+// A triangle mesh that has been validated once, so it can be marked and
+// rasterized without checking its indices again. See `TriMesh::new` for what
+// is validated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriMesh {
+  vertices: Vec<Vec3<f32>>,
+  triangles: Vec<Vec3<i32>>,
+}
+
+impl TriMesh {
+  // Creates a mesh from `triangles`, which contains i32 Vec3's where x,y,z
+  // corresponds to indices of `vertices`. Panics if any vertex has a NaN or
+  // infinite coordinate, if any triangle indices are out of range, or if any
+  // triangle is degenerate (it has zero area, e.g. it repeats a vertex).
+  pub fn new(vertices: Vec<Vec3<f32>>, triangles: Vec<Vec3<i32>>) -> Self {
+    for (index, vertex) in vertices.iter().enumerate() {
+      assert!(
+        vertex.x.is_finite() && vertex.y.is_finite() && vertex.z.is_finite(),
+        "Vertex has a non-finite coordinate. index={}, vertex={:?}",
+        index,
+        *vertex
+      );
+    }
+    assert_triangle_indices(&vertices, &triangles);
+    for (index, triangle) in triangles.iter().enumerate() {
+      let a = vertices[triangle.x as usize];
+      let b = vertices[triangle.y as usize];
+      let c = vertices[triangle.z as usize];
+      let (ab, ac) = (
+        Vec3::new(b.x - a.x, b.y - a.y, b.z - a.z),
+        Vec3::new(c.x - a.x, c.y - a.y, c.z - a.z),
+      );
+      let normal = Vec3::new(
+        ab.y * ac.z - ab.z * ac.y,
+        ab.z * ac.x - ab.x * ac.z,
+        ab.x * ac.y - ab.y * ac.x,
+      );
+      assert!(
+        normal != Vec3::new(0.0, 0.0, 0.0),
+        "Triangle is degenerate. index={}, Triangle={:?}",
+        index,
+        *triangle
+      );
+    }
+    Self { vertices, triangles }
+  }
+
+  pub fn vertices(&self) -> &[Vec3<f32>] {
+    &self.vertices
+  }
+
+  pub fn triangles(&self) -> &[Vec3<i32>] {
+    &self.triangles
+  }
+
+  // Returns the vertices and triangles, e.g. to modify them and validate them
+  // again.
+  pub fn into_parts(self) -> (Vec<Vec3<f32>>, Vec<Vec3<i32>>) {
+    (self.vertices, self.triangles)
+  }
+}
+
+// Panics if any index of `triangles` is out of range of `vertices`.
+pub(crate) fn assert_triangle_indices(
+  vertices: &[Vec3<f32>],
+  triangles: &[Vec3<i32>],
+) {
+  for triangle in triangles {
+    assert!(
+      0 <= triangle.x
+        && triangle.x < vertices.len() as i32
+        && 0 <= triangle.y
+        && triangle.y < vertices.len() as i32
+        && 0 <= triangle.z
+        && triangle.z < vertices.len() as i32,
+      "Triangle indexes out-of-bounds vertex. Triangle={:?}, vertices_len={}",
+      *triangle,
+      vertices.len()
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::panic::AssertUnwindSafe;
+
+  use crate::{
+    test_util::{collect_columns, new_heightfield},
+    util, Context, TriMesh, Vec3, WALKABLE_AREA_ID,
+  };
+
+  fn vertices() -> Vec<Vec3<f32>> {
+    vec![
+      Vec3::new(0.0, 0.5, 0.0),
+      Vec3::new(5.0, 0.5, 0.0),
+      Vec3::new(5.0, 0.5, 5.0),
+      Vec3::new(0.0, 0.5, 5.0),
+      // Steep.
+      Vec3::new(0.0, 5.0, 0.0),
+    ]
+  }
+
+  #[test]
+  fn rejects_invalid_meshes() {
+    let valid_triangle = Vec3::new(0, 2, 1);
+    let nan_vertices =
+      [Vec3::new(f32::NAN, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0)]
+        .into_iter()
+        .chain(vertices().into_iter().skip(2))
+        .collect::<Vec<_>>();
+    let infinite_vertices = vertices()
+      .into_iter()
+      .chain([Vec3::new(0.0, f32::INFINITY, 0.0)])
+      .collect::<Vec<_>>();
+
+    let invalid_meshes = [
+      (vertices(), Vec3::new(0, 2, 5)),
+      (vertices(), Vec3::new(-1, 2, 1)),
+      (vertices(), Vec3::new(0, 2, 2)),
+      // Collinear.
+      (
+        vec![
+          Vec3::new(0.0, 0.0, 0.0),
+          Vec3::new(1.0, 0.0, 1.0),
+          Vec3::new(2.0, 0.0, 2.0),
+        ],
+        Vec3::new(0, 1, 2),
+      ),
+      (nan_vertices, valid_triangle),
+      (infinite_vertices, valid_triangle),
+    ];
+
+    for (vertices, triangle) in invalid_meshes {
+      let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        TriMesh::new(vertices.clone(), vec![triangle]);
+      }));
+      assert!(
+        result.is_err(),
+        "Expected invalid mesh to break an assert, but succeeded. Triangle={:?}, vertices={:?}",
+        triangle,
+        vertices
+      );
+    }
+  }
+
+  #[test]
+  fn marks_and_rasterizes_tri_mesh() {
+    let mut context = Context::new();
+
+    let triangles =
+      vec![Vec3::new(0, 2, 1), Vec3::new(2, 0, 3), Vec3::new(3, 0, 4)];
+    let tri_mesh = TriMesh::new(vertices(), triangles.clone());
+    assert_eq!(tri_mesh.vertices(), vertices());
+    assert_eq!(tri_mesh.triangles(), triangles);
+
+    let mut area_ids = [0; 3];
+    util::mark_walkable_tri_mesh(&mut context, 45.0, &tri_mesh, &mut area_ids);
+    assert_eq!(area_ids, [WALKABLE_AREA_ID, WALKABLE_AREA_ID, 0]);
+
+    let mut area_ids = [1, 2, 3];
+    util::clear_unwalkable_tri_mesh(
+      &mut context,
+      45.0,
+      &tri_mesh,
+      &mut area_ids,
+    );
+    assert_eq!(area_ids, [1, 2, 0]);
+
+    let mut expected_heightfield =
+      new_heightfield(&mut context, Vec3::new(5.0, 5.0, 5.0));
+    expected_heightfield
+      .rasterize_indexed_triangles_i32(
+        &mut context,
+        &vertices(),
+        &triangles,
+        &area_ids,
+        1,
+      )
+      .expect("rasterization succeeds");
+
+    let mut heightfield =
+      new_heightfield(&mut context, Vec3::new(5.0, 5.0, 5.0));
+    heightfield
+      .rasterize_tri_mesh(&mut context, &tri_mesh, &area_ids, 1)
+      .expect("rasterization succeeds");
+    assert_eq!(
+      collect_columns(&heightfield),
+      collect_columns(&expected_heightfield)
+    );
+  }
+}
@@ -2,9 +2,10 @@ use std::ops::DerefMut;
 
 use recastnavigation_sys::{
   rcCalcBounds, rcClearUnwalkableTriangles, rcMarkWalkableTriangles,
+  rcOffsetPoly,
 };
 
-use crate::{Context, Vec3};
+use crate::{tri_mesh, Context, TriMesh, Vec3};
 
 // Computes the bounds of the provided `vertices`. The returned tuple is
 // `(min_bounds, max_bounds)`.
@@ -27,6 +28,37 @@ pub fn calculate_bounds(vertices: &[Vec3<f32>]) -> (Vec3<f32>, Vec3<f32>) {
   (min_bounds, max_bounds)
 }
 
+// Expands the convex polygon defined by `vertices` by `offset` (in world units)
+// on the XZ plane. This is generally used to grow convex area volumes by the
+// agent radius before marking them. The polygon is expanded when its vertices
+// are ordered like (0, 0), (1, 0), (1, 1) on the XZ plane (the opposite winding
+// shrinks it instead). Corners sharper than Recast's mitre limit (e.g. right
+// angles) are bevelled by replacing the corner with two vertices, so the result
+// can have up to twice as many vertices as `vertices`. Vertices keep their
+// original Y component.
+pub fn offset_polygon(vertices: &[Vec3<f32>], offset: f32) -> Vec<Vec3<f32>> {
+  let max_out_vertices = vertices.len() * 2;
+  let mut out_vertices =
+    vec![Vec3::<f32>::new(0.0, 0.0, 0.0); max_out_vertices];
+
+  // SAFETY: `rcOffsetPoly` only reads `vertices.len()` vertices from
+  // `vertices` and writes at most `max_out_vertices` vertices to
+  // `out_vertices`, which has exactly that many. All f32 pointers are read as
+  // 3 floats per vertex, which is the same as Vec3<f32>.
+  let out_vertices_len = unsafe {
+    rcOffsetPoly(
+      vertices.as_ptr() as *const f32,
+      vertices.len() as i32,
+      offset,
+      out_vertices.as_mut_ptr() as *mut f32,
+      max_out_vertices as i32,
+    )
+  };
+
+  out_vertices.truncate(out_vertices_len as usize);
+  out_vertices
+}
+
 // Marks triangles as walkable if their slope is less than
 // `walkable_slope_angle`. Each triangle contains 3 indices that index into
 // `vertices`. `WALKABLE_AREA_ID` will be stored in `triangle_area_ids` in the
@@ -73,19 +105,7 @@ pub fn mark_walkable_triangles(
   triangles: &[Vec3<i32>],
   triangle_area_ids: &mut [u8],
 ) {
-  for triangle in triangles {
-    assert!(
-      0 <= triangle.x
-        && triangle.x <= vertices.len() as i32
-        && 0 <= triangle.y
-        && triangle.y <= vertices.len() as i32
-        && 0 <= triangle.z
-        && triangle.z <= vertices.len() as i32,
-      "Triangle indexes out-of-bounds vertex. Triangle={:?}, vertices_len={}",
-      *triangle,
-      vertices.len()
-    );
-  }
+  tri_mesh::assert_triangle_indices(vertices, triangles);
 
   // SAFETY: We have checked that all indices in `triangles` are valid.
   // Therefore, the function is guaranteed to be safe.
@@ -100,6 +120,26 @@ pub fn mark_walkable_triangles(
   };
 }
 
+// Same as `mark_walkable_triangles`, but for the triangles of `tri_mesh`.
+// Since TriMesh validates its indices, they are not checked again.
+pub fn mark_walkable_tri_mesh(
+  context: &mut Context,
+  walkable_slope_angle: f32,
+  tri_mesh: &TriMesh,
+  triangle_area_ids: &mut [u8],
+) {
+  // SAFETY: TriMesh checks that all its triangle indices are in range.
+  unsafe {
+    mark_walkable_triangles_unchecked(
+      context,
+      walkable_slope_angle,
+      tri_mesh.vertices(),
+      tri_mesh.triangles(),
+      triangle_area_ids,
+    )
+  };
+}
+
 // Same as `mark_walkable_triangles_unchecked`, except it marks triangles
 // unwalkable (`INVALID_AREA_ID`) if they are steeper than
 // `walkable_slope_angle`.
@@ -144,19 +184,7 @@ pub fn clear_unwalkable_triangles(
   triangles: &[Vec3<i32>],
   triangle_area_ids: &mut [u8],
 ) {
-  for triangle in triangles {
-    assert!(
-      0 <= triangle.x
-        && triangle.x <= vertices.len() as i32
-        && 0 <= triangle.y
-        && triangle.y <= vertices.len() as i32
-        && 0 <= triangle.z
-        && triangle.z <= vertices.len() as i32,
-      "Triangle indexes out-of-bounds vertex. Triangle={:?}, vertices_len={}",
-      *triangle,
-      vertices.len()
-    );
-  }
+  tri_mesh::assert_triangle_indices(vertices, triangles);
 
   // SAFETY: We have checked that all indices in `triangles` are valid.
   // Therefore, the function is guaranteed to be safe.
@@ -171,6 +199,105 @@ pub fn clear_unwalkable_triangles(
   };
 }
 
+// Same as `clear_unwalkable_triangles`, but for the triangles of `tri_mesh`.
+// Since TriMesh validates its indices, they are not checked again.
+pub fn clear_unwalkable_tri_mesh(
+  context: &mut Context,
+  walkable_slope_angle: f32,
+  tri_mesh: &TriMesh,
+  triangle_area_ids: &mut [u8],
+) {
+  // SAFETY: TriMesh checks that all its triangle indices are in range.
+  unsafe {
+    clear_unwalkable_triangles_unchecked(
+      context,
+      walkable_slope_angle,
+      tri_mesh.vertices(),
+      tri_mesh.triangles(),
+      triangle_area_ids,
+    )
+  };
+}
+
+// Information about a triangle passed to the callback of
+// `classify_triangles`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TriangleInfo<Material> {
+  // The index of the triangle.
+  pub index: usize,
+  // The unit normal of the triangle, computed as `(v1 - v0) x (v2 - v0)` like
+  // `mark_walkable_triangles`. Degenerate triangles have a zero normal.
+  pub normal: Vec3<f32>,
+  // The angle between the normal and the up axis in degrees (0 for flat
+  // triangles facing up, 180 for flat triangles facing down, and 90 for walls
+  // and degenerate triangles).
+  pub slope_angle: f32,
+  // The average of the triangle's vertices.
+  pub centroid: Vec3<f32>,
+  // The material of the triangle from the `materials` passed to
+  // `classify_triangles`.
+  pub material: Material,
+}
+
+// Computes the area ID of each triangle by calling `classify` with information
+// about the triangle. `materials` must hold one material per triangle, which
+// can be any tag (e.g. a material enum) used to choose the area ID. For
+// example, this can map materials to area IDs with a different walkable slope
+// angle per material in one pass. Panics if any triangle indices are out of
+// range.
+pub fn classify_triangles<Material: Copy>(
+  vertices: &[Vec3<f32>],
+  triangles: &[Vec3<i32>],
+  materials: &[Material],
+  mut classify: impl FnMut(&TriangleInfo<Material>) -> u8,
+) -> Vec<u8> {
+  assert_eq!(
+    triangles.len(),
+    materials.len(),
+    "Each triangle should have one material."
+  );
+  tri_mesh::assert_triangle_indices(vertices, triangles);
+
+  triangles
+    .iter()
+    .zip(materials.iter())
+    .enumerate()
+    .map(|(index, (triangle, &material))| {
+      let v0 = vertices[triangle.x as usize];
+      let v1 = vertices[triangle.y as usize];
+      let v2 = vertices[triangle.z as usize];
+
+      let e0 = Vec3::new(v1.x - v0.x, v1.y - v0.y, v1.z - v0.z);
+      let e1 = Vec3::new(v2.x - v0.x, v2.y - v0.y, v2.z - v0.z);
+      let normal = Vec3::new(
+        e0.y * e1.z - e0.z * e1.y,
+        e0.z * e1.x - e0.x * e1.z,
+        e0.x * e1.y - e0.y * e1.x,
+      );
+      let length =
+        (normal.x * normal.x + normal.y * normal.y + normal.z * normal.z)
+          .sqrt();
+      let normal = if length > 0.0 {
+        Vec3::new(normal.x / length, normal.y / length, normal.z / length)
+      } else {
+        normal
+      };
+
+      classify(&TriangleInfo {
+        index,
+        normal,
+        slope_angle: normal.y.clamp(-1.0, 1.0).acos().to_degrees(),
+        centroid: Vec3::new(
+          (v0.x + v1.x + v2.x) / 3.0,
+          (v0.y + v1.y + v2.y) / 3.0,
+          (v0.z + v1.z + v2.z) / 3.0,
+        ),
+        material,
+      })
+    })
+    .collect()
+}
+
 #[cfg(test)]
 mod tests {
   use std::panic::AssertUnwindSafe;
@@ -190,6 +317,87 @@ mod tests {
     );
   }
 
+  #[test]
+  fn offset_polygon_bevels_sharp_corners() {
+    let square = [
+      Vec3::new(0.0, 1.0, 0.0),
+      Vec3::new(2.0, 1.0, 0.0),
+      Vec3::new(2.0, 1.0, 2.0),
+      Vec3::new(0.0, 1.0, 2.0),
+    ];
+
+    // Right angles exceed the mitre limit, so each corner is replaced by two
+    // vertices.
+    assert_eq!(
+      util::offset_polygon(&square, 1.0),
+      [
+        Vec3::new(-1.0, 1.0, -0.5),
+        Vec3::new(-0.5, 1.0, -1.0),
+        Vec3::new(2.5, 1.0, -1.0),
+        Vec3::new(3.0, 1.0, -0.5),
+        Vec3::new(3.0, 1.0, 2.5),
+        Vec3::new(2.5, 1.0, 3.0),
+        Vec3::new(-0.5, 1.0, 3.0),
+        Vec3::new(-1.0, 1.0, 2.5),
+      ]
+    );
+  }
+
+  #[test]
+  fn offset_polygon_mitres_shallow_corners() {
+    let hexagon = (0..6)
+      .map(|i| {
+        let angle = i as f32 * std::f32::consts::PI / 3.0;
+        Vec3::new(2.0 * angle.cos(), 1.0, 2.0 * angle.sin())
+      })
+      .collect::<Vec<_>>();
+
+    let offset_hexagon = util::offset_polygon(&hexagon, 0.5);
+
+    // Hexagon corners are within the mitre limit, so each corner is moved
+    // outwards such that every edge is exactly `offset` further out.
+    assert_eq!(offset_hexagon.len(), 6);
+    let expected_radius = 2.0 + 0.5 / (std::f32::consts::PI / 6.0).cos();
+    for (vertex, offset_vertex) in hexagon.iter().zip(offset_hexagon.iter()) {
+      let radius = (offset_vertex.x * offset_vertex.x
+        + offset_vertex.z * offset_vertex.z)
+        .sqrt();
+      assert!(
+        (radius - expected_radius).abs() < 1e-4,
+        "\n\nleft: {:?}\nright: {:?}",
+        radius,
+        expected_radius
+      );
+      assert!(
+        (vertex.x * offset_vertex.z - vertex.z * offset_vertex.x).abs() < 1e-4,
+        "Vertex was not offset along its corner direction. vertex={:?}, offset_vertex={:?}",
+        vertex,
+        offset_vertex
+      );
+      assert_eq!(offset_vertex.y, 1.0);
+    }
+  }
+
+  #[test]
+  fn offset_polygon_with_opposite_winding_shrinks() {
+    let square = [
+      Vec3::new(0.0, 0.0, 2.0),
+      Vec3::new(2.0, 0.0, 2.0),
+      Vec3::new(2.0, 0.0, 0.0),
+      Vec3::new(0.0, 0.0, 0.0),
+    ];
+
+    assert_eq!(
+      util::offset_polygon(&square, 0.5),
+      [
+        Vec3::new(0.5, 0.0, 1.5),
+        Vec3::new(1.5, 0.0, 1.5),
+        Vec3::new(1.5, 0.0, 0.5),
+        Vec3::new(0.5, 0.0, 0.5),
+      ]
+    );
+  }
+
   #[test]
   fn marks_walkable_triangles() {
     let mut context = Context::new();
@@ -264,6 +472,7 @@ mod tests {
       Vec3::new(0, 20, 2),
       Vec3::new(0, 1, -3),
       Vec3::new(0, 1, 30),
+      Vec3::new(0, 1, 3),
     ];
 
     for invalid_triangle_slice in invalid_triangles.chunks(1) {
@@ -358,6 +567,7 @@ mod tests {
       Vec3::new(0, 20, 2),
       Vec3::new(0, 1, -3),
       Vec3::new(0, 1, 30),
+      Vec3::new(0, 1, 3),
     ];
 
     for invalid_triangle_slice in invalid_triangles.chunks(1) {
@@ -377,4 +587,75 @@ mod tests {
       );
     }
   }
+
+  #[test]
+  fn classifies_triangles() {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Material {
+      Mud,
+      Water,
+      Ice,
+    }
+
+    let vertices = [
+      Vec3::new(0.0, 0.0, 0.0),
+      Vec3::new(1.0, 0.0, 0.0),
+      Vec3::new(1.0, 0.0, 1.0),
+      Vec3::new(0.0, 0.0, 1.0),
+      // A 30 degree slope.
+      Vec3::new(3.0, 2.0 / 3.0f32.sqrt(), 0.0),
+      Vec3::new(3.0, 2.0 / 3.0f32.sqrt(), 1.0),
+    ];
+    let triangles = [
+      Vec3::new(0, 2, 1),
+      Vec3::new(2, 0, 3),
+      Vec3::new(1, 5, 4),
+      Vec3::new(5, 1, 2),
+      // Facing down.
+      Vec3::new(0, 1, 2),
+    ];
+    let materials = [
+      Material::Water,
+      Material::Mud,
+      Material::Mud,
+      Material::Ice,
+      Material::Mud,
+    ];
+
+    let mut infos = vec![];
+    let area_ids =
+      util::classify_triangles(&vertices, &triangles, &materials, |info| {
+        infos.push(*info);
+        let max_slope_angle = match info.material {
+          Material::Water => return 3,
+          Material::Mud => 45.0,
+          Material::Ice => 20.0,
+        };
+        if info.slope_angle > max_slope_angle {
+          crate::INVALID_AREA_ID
+        } else if info.material == Material::Ice {
+          2
+        } else {
+          1
+        }
+      });
+    assert_eq!(area_ids, [3, 1, 1, 0, 0]);
+
+    assert_eq!(infos.len(), 5);
+    assert_eq!(
+      infos.iter().map(|info| info.index).collect::<Vec<_>>(),
+      [0, 1, 2, 3, 4]
+    );
+    assert_eq!(infos[0].normal, Vec3::new(0.0, 1.0, 0.0));
+    assert_eq!(infos[0].slope_angle, 0.0);
+    assert_eq!(infos[0].centroid, Vec3::new(2.0 / 3.0, 0.0, 1.0 / 3.0));
+    assert!(
+      (infos[2].slope_angle - 30.0).abs() < 1e-4,
+      "\n\nleft: {:?}\nright: {:?}",
+      infos[2].slope_angle,
+      30.0
+    );
+    assert_eq!(infos[4].normal, Vec3::new(0.0, -1.0, 0.0));
+    assert_eq!(infos[4].slope_angle, 180.0);
+  }
 }
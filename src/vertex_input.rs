@@ -0,0 +1,180 @@
+use crate::Vec3;
+
+// An index into a list of vertices. Implemented for the index types commonly
+// used by index buffers.
+pub trait TriangleIndex: Copy + std::fmt::Debug {
+  // Returns the index as a usize, or None if it cannot be one (e.g. negative).
+  fn to_usize(self) -> Option<usize>;
+}
+
+impl TriangleIndex for u16 {
+  fn to_usize(self) -> Option<usize> {
+    Some(self as usize)
+  }
+}
+
+impl TriangleIndex for u32 {
+  fn to_usize(self) -> Option<usize> {
+    usize::try_from(self).ok()
+  }
+}
+
+impl TriangleIndex for i32 {
+  fn to_usize(self) -> Option<usize> {
+    usize::try_from(self).ok()
+  }
+}
+
+impl TriangleIndex for usize {
+  fn to_usize(self) -> Option<usize> {
+    Some(self)
+  }
+}
+
+// A view of vertex positions stored in a buffer of floats, possibly
+// interleaved with other vertex data (e.g. normals and UVs). The position of
+// vertex `i` is the 3 floats starting at `offset + i * stride`.
+#[derive(Debug, Clone, Copy)]
+pub struct VertexPositions<'data> {
+  data: &'data [f32],
+  offset: usize,
+  stride: usize,
+  len: usize,
+}
+
+impl<'data> VertexPositions<'data> {
+  // Creates a view of the positions in `data`, where `offset` and `stride` are
+  // measured in floats. Every complete position in `data` is a vertex. Panics
+  // if `stride` is less than 3 (positions would overlap).
+  pub fn new(data: &'data [f32], offset: usize, stride: usize) -> Self {
+    assert!(stride >= 3, "Stride must fit a position. stride={}", stride);
+    let len = match data.len().checked_sub(offset + 3) {
+      None => 0,
+      Some(remaining) => remaining / stride + 1,
+    };
+    Self { data, offset, stride, len }
+  }
+
+  // Creates a view of tightly packed positions.
+  pub fn from_vec3s(vertices: &'data [Vec3<f32>]) -> Self {
+    // SAFETY: Vec3<f32> is repr(C) with 3 f32 fields, so a slice of them has
+    // the same layout as a slice of 3 times as many f32s.
+    let data = unsafe {
+      std::slice::from_raw_parts(
+        vertices.as_ptr() as *const f32,
+        vertices.len() * 3,
+      )
+    };
+    Self::new(data, 0, 3)
+  }
+
+  // The number of vertices.
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  // Returns the position of the vertex at `index`. Panics if `index` is out of
+  // range.
+  pub fn get(&self, index: usize) -> Vec3<f32> {
+    assert!(
+      index < self.len,
+      "Vertex index out-of-bounds. index={}, len={}",
+      index,
+      self.len
+    );
+    let start = self.offset + index * self.stride;
+    Vec3::new(self.data[start], self.data[start + 1], self.data[start + 2])
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::panic::AssertUnwindSafe;
+
+  use crate::{
+    test_util::{collect_columns, new_heightfield},
+    Context, Vec3, VertexPositions, WALKABLE_AREA_ID,
+  };
+
+  #[test]
+  fn rasterizes_strided_vertices() {
+    let mut context = Context::new();
+
+    let vertices = [
+      Vec3::new(0.0, 0.5, 0.0),
+      Vec3::new(5.0, 0.5, 0.0),
+      Vec3::new(5.0, 2.5, 5.0),
+      Vec3::new(0.0, 2.5, 5.0),
+    ];
+    // Each vertex is followed by a UV.
+    let interleaved = vertices
+      .iter()
+      .flat_map(|vertex| [vertex.x, vertex.y, vertex.z, 0.25, 0.75])
+      .collect::<Vec<_>>();
+    let positions = VertexPositions::new(&interleaved, 0, 5);
+    assert_eq!(positions.len(), 4);
+    assert_eq!(positions.get(2), vertices[2]);
+
+    let area_ids = [WALKABLE_AREA_ID, 1];
+
+    let mut expected_heightfield =
+      new_heightfield(&mut context, Vec3::new(5.0, 5.0, 5.0));
+    expected_heightfield
+      .rasterize_indexed_triangles_i32(
+        &mut context,
+        &vertices,
+        &[Vec3::new(0, 2, 1), Vec3::new(2, 0, 3)],
+        &area_ids,
+        1,
+      )
+      .expect("rasterization succeeds");
+    let expected_columns = collect_columns(&expected_heightfield);
+
+    let mut heightfield =
+      new_heightfield(&mut context, Vec3::new(5.0, 5.0, 5.0));
+    heightfield
+      .rasterize_indexed_triangles(
+        &mut context,
+        positions,
+        &[0u32, 2, 1, 2, 0, 3],
+        &area_ids,
+        1,
+      )
+      .expect("rasterization succeeds");
+    assert_eq!(collect_columns(&heightfield), expected_columns);
+
+    let mut heightfield =
+      new_heightfield(&mut context, Vec3::new(5.0, 5.0, 5.0));
+    heightfield
+      .rasterize_indexed_triangles(
+        &mut context,
+        VertexPositions::from_vec3s(&vertices),
+        &[0usize, 2, 1, 2, 0, 3],
+        &area_ids,
+        1,
+      )
+      .expect("rasterization succeeds");
+    assert_eq!(collect_columns(&heightfield), expected_columns);
+
+    for invalid_indices in [[0, 4, 1], [0, -1, 1]] {
+      let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        let _ = heightfield.rasterize_indexed_triangles(
+          &mut context,
+          positions,
+          &invalid_indices,
+          &[WALKABLE_AREA_ID],
+          1,
+        );
+      }));
+      assert!(
+        result.is_err(),
+        "Expected invalid triangle to break an assert, but succeeded. Triangle={:?}",
+        invalid_indices
+      );
+    }
+  }
+}